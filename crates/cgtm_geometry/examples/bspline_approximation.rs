@@ -2,7 +2,7 @@ use cgtm_geometry::curve::bspline::bspline_approximation::BSplineApproximation;
 use cgtm_geometry::curve::bspline::bspline_data_knots::BSplineDataKnotsAlgo;
 use cgtm_geometry::curve::bspline::bspline_knots::{BSplineFeature, BSplineKnotsAlgo};
 use cgtm_geometry::curve::bspline::BSpline;
-use f3l::glam::Vec3;
+use cgtm_geometry::precision::{Real, Vec3};
 #[cfg(feature = "app")]
 use kiss3d::light::Light;
 #[cfg(feature = "app")]
@@ -21,21 +21,21 @@ fn main() {
     let green = Point3::new(0., 1., 0.);
     let data = gen_data();
     let bspline = approximation(&data);
-    let (lower, upper) = (bspline.knots.lower, bspline.knots.upper);
-    let low_v = bspline.knots.knots[lower].value;
-    let upp_v = bspline.knots.knots[upper].value;
+    let low_v = bspline.lower_parameter();
+    let upp_v = bspline.upper_parameter();
     let d = upp_v - low_v;
 
     let ori = data
         .iter()
-        .map(|p| Point3::new(p.x, p.y, p.z))
+        .map(|p| Point3::new(p.x, p.y, p.z).cast::<f32>())
         .collect::<Vec<_>>();
-    let pts = (0..400)
-        .map(|i| {
-            let u = (i as f32) / 400f32 * d + low_v;
-            let p = bspline.interop(u);
-            Point3::new(p.x, p.y, p.z)
-        })
+    let us = (0..400)
+        .map(|i| (i as Real) / 400. * d + low_v)
+        .collect::<Vec<_>>();
+    let pts = bspline
+        .evaluate_many(&us)
+        .into_iter()
+        .map(|p| Point3::new(p.x, p.y, p.z).cast::<f32>())
         .collect::<Vec<_>>();
 
     while window.render() {
@@ -50,12 +50,12 @@ fn main() {
 
 fn gen_data() -> Vec<Vec3> {
     let samples = 400;
-    let start = -2f32;
-    let end = 2f32;
-    let d = (end - start) / samples as f32;
+    let start = -2.;
+    let end = 2.;
+    let d = (end - start) / samples as Real;
     (0..samples)
         .map(|x| {
-            let x = x as f32 * d;
+            let x = x as Real * d;
             let y = x.sin();
             Vec3::new(x, y, 0.)
         })
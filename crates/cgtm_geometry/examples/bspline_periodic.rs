@@ -1,7 +1,7 @@
 use cgtm_geometry::curve::bspline::bspline_knots::{BSplineKnot, BSplineKnots};
 use cgtm_geometry::curve::bspline::bspline_poles::BSplinePole;
 use cgtm_geometry::curve::bspline::BSpline;
-use f3l::glam::Vec3;
+use cgtm_geometry::precision::{Real, Vec3};
 #[cfg(feature = "app")]
 use kiss3d::light::Light;
 #[cfg(feature = "app")]
@@ -18,16 +18,16 @@ fn main() {
 
     let white = Point3::new(1., 1., 1.);
     let bspline = bspline_periodic();
-    let (lower, upper) = (bspline.knots.lower, bspline.knots.upper);
-    let low_v = bspline.knots.knots[lower].value;
-    let upp_v = bspline.knots.knots[upper].value;
+    let low_v = bspline.lower_parameter();
+    let upp_v = bspline.upper_parameter();
     let d = upp_v - low_v;
-    let pts = (0..=100)
-        .map(|i| {
-            let u = (i as f32) / 100f32 * d + low_v;
-            let p = bspline.interop(u);
-            Point3::new(p.x, p.y, p.z)
-        })
+    let us = (0..=100)
+        .map(|i| (i as Real) / 100. * d + low_v)
+        .collect::<Vec<_>>();
+    let pts = bspline
+        .evaluate_many(&us)
+        .into_iter()
+        .map(|p| Point3::new(p.x, p.y, p.z).cast::<f32>())
         .collect::<Vec<_>>();
 
     while window.render() {
@@ -105,11 +105,5 @@ fn bspline_periodic() -> BSpline {
         },
     ];
 
-    BSpline {
-        degree,
-        is_periodic: true,
-        knots,
-        poles,
-        use_rational: false,
-    }
+    BSpline::try_new(degree, knots, poles, false).unwrap()
 }
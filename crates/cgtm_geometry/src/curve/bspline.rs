@@ -1,88 +1,420 @@
+use crate::precision::{Real, Vec3};
+use bspline_error::BSplineError;
 use bspline_knots::BSplineKnots;
-use bspline_poles::BSplinePole;
-use f3l::glam::Vec3;
-use utils::{compute_coefficients, de_boor};
+use bspline_poles::{BSplinePole, ControlPoint};
+use utils::{basis_functions, de_boor_derivatives, de_boor_in_place};
 
 pub mod bspline_approximation;
+pub mod bspline_blend;
+pub mod bspline_builder;
 pub mod bspline_data_knots;
+pub mod bspline_domain;
 pub mod bspline_error;
+pub mod bspline_evaluation;
+pub mod bspline_interpolation;
+pub mod bspline_intersection;
+pub mod bspline_join;
 pub mod bspline_knots;
+pub mod bspline_offset;
 pub mod bspline_poles;
+pub mod bspline_refinement;
+pub mod bspline_transform;
 pub mod utils;
 
+/// Degrees below this are evaluated in stack buffers by [`BSpline::interop`].
+pub const STACK_POLES: usize = 16;
+
+/// B-spline curve, `P` is the type of its poles, 3D by default.
+///
+/// Built by [`BSpline::try_new`] or [`BSplineBuilder`], the knots always match the
+/// degree and the number of poles.
+///
+/// [`BSplineBuilder`]: bspline_builder::BSplineBuilder
 #[derive(Debug, Clone)]
-pub struct BSpline {
-    pub degree: usize,
-    pub knots: BSplineKnots,
-    pub poles: Vec<BSplinePole>,
-    pub is_periodic: bool,
-    pub use_rational: bool,
+pub struct BSpline<P = Vec3> {
+    pub(crate) degree: usize,
+    pub(crate) knots: BSplineKnots,
+    pub(crate) poles: Vec<BSplinePole<P>>,
+    pub(crate) is_periodic: bool,
+    pub(crate) use_rational: bool,
 }
 
-impl BSpline {
-    fn find_pole_index(
-        knots: &BSplineKnots,
-        knot_index: usize,
+impl<P: ControlPoint> BSpline<P> {
+    /// Curve of `degree` with `knots` and `poles`, periodic if the knots are.
+    ///
+    /// Fails if `knots` were not built for `degree` and `poles.len()`, if a pole is not
+    /// finite, or if rational with a weight that is not finite and positive.
+    pub fn try_new(
         degree: usize,
-        is_periodic: bool,
-    ) -> usize {
-        let pole_index = knots
-            .knots
-            .iter()
-            .take(knot_index + 1)
-            .map(|knot| knot.multiplicity)
-            .sum::<usize>();
-        if is_periodic {
-            pole_index - knots.knots[knots.lower].multiplicity
-        } else {
-            pole_index - (degree + 1)
+        knots: BSplineKnots,
+        poles: Vec<BSplinePole<P>>,
+        use_rational: bool,
+    ) -> Result<Self, BSplineError> {
+        knots.check_for(degree, poles.len())?;
+        for (index, p) in poles.iter().enumerate() {
+            check_pole(index, p, use_rational)?;
         }
+        Ok(Self {
+            degree,
+            is_periodic: knots.is_periodic(),
+            knots,
+            poles,
+            use_rational,
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    pub fn knots(&self) -> &BSplineKnots {
+        &self.knots
+    }
+
+    pub fn poles(&self) -> &[BSplinePole<P>] {
+        &self.poles
     }
 
-    fn get_poles(&self, pole_index: usize) -> Vec<BSplinePole> {
+    /// Replaces pole `index`, checked like the poles of [`BSpline::try_new`].
+    pub fn try_set_pole(&mut self, index: usize, pole: BSplinePole<P>) -> Result<(), BSplineError> {
+        let nb_poles = self.poles.len();
+        let slot = self
+            .poles
+            .get_mut(index)
+            .ok_or(BSplineError::PoleIndexOutOfRange { index, nb_poles })?;
+        check_pole(index, &pole, self.use_rational)?;
+        *slot = pole;
+        Ok(())
+    }
+
+    pub fn is_periodic(&self) -> bool {
+        self.is_periodic
+    }
+
+    pub fn use_rational(&self) -> bool {
+        self.use_rational
+    }
+
+    fn get_poles(&self, pole_index: usize) -> Vec<BSplinePole<P>> {
+        let mut poles = vec![BSplinePole::default(); self.degree + 1];
+        self.fill_poles(pole_index, &mut poles);
+        poles
+    }
+
+    /// Copies the `degree + 1` poles from `pole_index` into `out`, weighted if rational.
+    pub(crate) fn fill_poles(&self, pole_index: usize, out: &mut [BSplinePole<P>]) {
         let mut pi = pole_index;
-        (0..=self.degree)
-            .map(|_| {
-                if pi > self.poles.len() - 1 {
-                    pi = 0;
-                }
-                let mut p = self.poles[pi];
-                if self.use_rational {
-                    p.pole *= p.weight;
-                }
-                pi += 1;
-                p
-            })
-            .collect()
+        out.iter_mut().take(self.degree + 1).for_each(|out| {
+            if pi > self.poles.len() - 1 {
+                pi = 0;
+            }
+            let mut p = self.poles[pi];
+            if self.use_rational {
+                p.pole = p.pole * p.weight;
+            }
+            pi += 1;
+            *out = p;
+        });
     }
 
-    pub fn interop(&self, u: f32) -> Vec3 {
-        let knot_index = self.knots.get_knot_index(u);
+    /// Index of the knot starting the span of `u`, parameters past the domain use the
+    /// first or last span.
+    pub(crate) fn knot_index(&self, u: Real) -> usize {
+        self.knots.get_span_index(u)
+    }
+
+    /// `u` shifted into the domain by the period when periodic, unchanged otherwise.
+    pub(crate) fn periodic_parameter(&self, u: Real) -> Real {
+        let domain = self.domain();
+        if self.is_periodic && u.is_finite() && !domain.contains(u) {
+            domain.wrap(u)
+        } else {
+            u
+        }
+    }
+
+    pub(crate) fn pole_index(&self, knot_index: usize) -> usize {
+        self.knots
+            .get_pole_index(knot_index, self.degree, self.is_periodic)
+    }
+
+    fn local_span(&self, u: Real) -> (Vec<Real>, Vec<BSplinePole<P>>) {
+        let knot_index = self.knot_index(u);
         let knot_slice = self
             .knots
             .get_knots_bounds(knot_index, self.degree, self.is_periodic);
-        let pole_index =
-            Self::find_pole_index(&self.knots, knot_index, self.degree, self.is_periodic);
-        let poles = self.get_poles(pole_index);
+        (knot_slice, self.get_poles(self.pole_index(knot_index)))
+    }
+
+    /// Point `C(u)`.
+    ///
+    /// Parameters outside the domain wrap by the period when periodic, and otherwise
+    /// extend the first or last span without check, see [`BSpline::try_evaluate`] for
+    /// other policies.
+    /// Does not allocate for degrees below [`STACK_POLES`].
+    pub fn interop(&self, u: Real) -> P {
+        self.interop_extended(self.periodic_parameter(u))
+    }
+
+    /// Same as [`BSpline::interop`], periodic parameters outside the domain extend the
+    /// first or last span instead of wrapping.
+    pub(crate) fn interop_extended(&self, u: Real) -> P {
+        if self.degree < STACK_POLES {
+            let mut scratch = [BSplinePole::default(); STACK_POLES];
+            self.interop_extended_with(u, &mut scratch)
+        } else {
+            self.interop_extended_with(u, &mut vec![BSplinePole::default(); self.degree + 1])
+        }
+    }
+
+    /// Same as [`BSpline::interop`], using `scratch` as working space.
+    ///
+    /// Panics if `scratch` holds less than `degree + 1` poles.
+    pub fn interop_with(&self, u: Real, scratch: &mut [BSplinePole<P>]) -> P {
+        self.interop_extended_with(self.periodic_parameter(u), scratch)
+    }
+
+    fn interop_extended_with(&self, u: Real, scratch: &mut [BSplinePole<P>]) -> P {
+        let knot_index = self.knot_index(u);
+        let knot_slice =
+            self.knots
+                .get_knots_bounds_slice(knot_index, self.degree, self.is_periodic);
+        self.fill_poles(self.pole_index(knot_index), scratch);
+        de_boor_in_place(u, knot_slice, scratch, self.degree, self.use_rational)
+    }
 
-        de_boor(u, &knot_slice, &poles, self.degree, self.use_rational)
+    /// Derivatives `0..=order` of the homogeneous curve `(w C(u), w(u))`, `w = 1` when
+    /// not rational.
+    pub(crate) fn homogeneous_derivatives(&self, u: Real, order: usize) -> Vec<BSplinePole<P>> {
+        self.homogeneous_derivatives_extended(self.periodic_parameter(u), order)
     }
 
-    pub fn lower_parameter(&self) -> f32 {
+    fn homogeneous_derivatives_extended(&self, u: Real, order: usize) -> Vec<BSplinePole<P>> {
+        let (knot_slice, poles) = self.local_span(u);
+        let mut ders = de_boor_derivatives(u, &knot_slice, &poles, self.degree, order);
+        if !self.use_rational {
+            ders.iter_mut()
+                .enumerate()
+                .for_each(|(k, d)| d.weight = if k == 0 { 1. } else { 0. });
+        }
+        ders
+    }
+
+    /// Point and derivatives `C(u), C'(u), ..., C^(order)(u)`.
+    ///
+    /// Parameters outside the domain are handled like [`BSpline::interop`].
+    pub fn derivatives(&self, u: Real, order: usize) -> Vec<P> {
+        self.derivatives_extended(self.periodic_parameter(u), order)
+    }
+
+    /// Same as [`BSpline::derivatives`], periodic parameters outside the domain extend
+    /// the first or last span instead of wrapping.
+    pub(crate) fn derivatives_extended(&self, u: Real, order: usize) -> Vec<P> {
+        let ders = self.homogeneous_derivatives_extended(u, order);
+        if !self.use_rational {
+            return ders.into_iter().map(|d| d.pole).collect();
+        }
+        // C^(k) = (A^(k) - sum_{i=1..k} binom(k, i) w^(i) C^(k-i)) / w
+        let mut cks: Vec<P> = Vec::with_capacity(order + 1);
+        for k in 0..=order {
+            let mut v = ders[k].pole;
+            let mut binom = 1.;
+            for i in 1..=k {
+                binom = binom * (k + 1 - i) as Real / i as Real;
+                v -= cks[k - i] * (binom * ders[i].weight);
+            }
+            cks.push(v / ders[0].weight);
+        }
+        cks
+    }
+
+    pub fn lower_parameter(&self) -> Real {
         self.knots.lower_value()
     }
 
-    pub fn upper_parameter(&self) -> f32 {
+    pub fn upper_parameter(&self) -> Real {
         self.knots.upper_value()
     }
 
-    pub fn coefficients(&self, u: f32) -> Vec<f32> {
-        compute_coefficients(
-            u,
-            &self.knots.flatten,
-            self.poles.len(),
-            self.degree,
-            self.knots.n_extend,
-        )
+    /// Reverses the direction of the curve, `C'(u) = C(lower + upper - u)`.
+    pub fn reverse(&mut self) {
+        self.knots.reverse();
+        self.poles.reverse();
+        if self.is_periodic {
+            // Pole `j` moves to `(degree - m0 - j) mod n`, `m0` the seam multiplicity.
+            let m0 = self.knots.knots[0].multiplicity;
+            let shift = (self.degree + 1 - m0) % self.poles.len();
+            self.poles.rotate_right(shift);
+        }
+    }
+
+    /// Remaps the parameter domain to `[new_lower, new_upper]`, the shape is unchanged.
+    pub fn reparameterize(&mut self, new_lower: Real, new_upper: Real) -> Result<(), BSplineError> {
+        self.knots.reparameterize(new_lower, new_upper)
+    }
+
+    /// Non-rational basis function of each pole at `u`, `C(u) = sum N_i(u) P_i` when not
+    /// rational.
+    ///
+    /// Spans are those of [`BSpline::interop`], periodic curves wrap the poles around the
+    /// seam and parameters outside the domain wrap or extend the first or last span.
+    pub fn coefficients(&self, u: Real) -> Vec<Real> {
+        let u = self.periodic_parameter(u);
+        let knot_index = self.knot_index(u);
+        let knot_slice =
+            self.knots
+                .get_knots_bounds_slice(knot_index, self.degree, self.is_periodic);
+        let first = self.pole_index(knot_index);
+        let mut coefficients = vec![0.; self.poles.len()];
+        // The span starts at `knot_slice[degree - 1]`.
+        basis_functions(self.degree.saturating_sub(1), u, knot_slice, self.degree)
+            .into_iter()
+            .enumerate()
+            .for_each(|(j, n)| coefficients[(first + j) % self.poles.len()] += n);
+        coefficients
+    }
+}
+
+/// Fails if pole `index` is not finite, or if rational with a weight that is not finite
+/// and positive.
+fn check_pole<P: ControlPoint>(
+    index: usize,
+    p: &BSplinePole<P>,
+    use_rational: bool,
+) -> Result<(), BSplineError> {
+    if (0..P::DIM).any(|i| !p.pole.coord(i).is_finite()) {
+        return Err(BSplineError::NonFinitePole { index });
+    }
+    if use_rational && !(p.weight.is_finite() && p.weight > 0.) {
+        return Err(BSplineError::NonPositiveWeight {
+            index,
+            weight: p.weight,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve(flatten: &[Real], degree: usize, poles: &[Vec3]) -> BSpline {
+        let knots = BSplineKnots::try_from_flatten(flatten, degree, poles.len(), false).unwrap();
+        let poles = poles
+            .iter()
+            .map(|&pole| BSplinePole { pole, weight: 1. })
+            .collect();
+        BSpline::try_new(degree, knots, poles, false).unwrap()
+    }
+
+    #[test]
+    fn reverse_unclamped() {
+        let poles = [
+            Vec3::new(0., 0., 0.),
+            Vec3::new(1., 2., 0.),
+            Vec3::new(3., -1., 1.),
+            Vec3::new(4., 1., 2.),
+        ];
+        let original = curve(&[0., 1., 2., 3., 5., 6., 9.], 2, &poles);
+        let mut reversed = original.clone();
+        reversed.reverse();
+        let (lower, upper) = (original.lower_parameter(), original.upper_parameter());
+        assert_eq!(reversed.lower_parameter(), lower);
+        assert_eq!(reversed.upper_parameter(), upper);
+        for i in 0..=10 {
+            let u = lower + (upper - lower) * i as Real / 10.;
+            let expected = original.interop(lower + upper - u);
+            assert!(reversed.interop(u).distance(expected) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn reverse_periodic() {
+        let flatten = [0., 1., 1., 2., 3.5, 4., 5.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 6, true).unwrap();
+        let poles = (0..6)
+            .map(|i| BSplinePole {
+                pole: Vec3::new((60. * i as Real).to_radians().cos(), i as Real, 0.),
+                weight: 1. + 0.2 * i as Real,
+            })
+            .collect();
+        let original = BSpline::try_new(3, knots, poles, true).unwrap();
+        let mut reversed = original.clone();
+        reversed.reverse();
+        assert!(reversed.is_periodic());
+        let (lower, upper) = (original.lower_parameter(), original.upper_parameter());
+        for i in 0..=20 {
+            let u = lower + (upper - lower) * i as Real / 20.;
+            let expected = original.interop(lower + upper - u);
+            assert!(reversed.interop(u).distance(expected) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn coefficients_match_evaluation() {
+        let clamped = curve(
+            &[0., 0., 0., 1., 3., 3., 3.],
+            2,
+            &[Vec3::ZERO, Vec3::Y, Vec3::new(2., 1., 0.), Vec3::X * 3.],
+        );
+        let knots =
+            BSplineKnots::try_from_flatten(&[0., 1., 2., 3., 4.5, 5., 6.], 3, 6, true).unwrap();
+        let poles = (0..6)
+            .map(|i| BSplinePole {
+                pole: Vec3::new((60. * i as Real).to_radians().cos(), 0.5 * i as Real, 1.),
+                weight: 1.,
+            })
+            .collect();
+        let periodic = BSpline::try_new(3, knots, poles, false).unwrap();
+        for bspline in [clamped, periodic] {
+            let (lower, upper) = (bspline.lower_parameter(), bspline.upper_parameter());
+            for i in -2..=22 {
+                let u = lower + (upper - lower) * i as Real / 20.;
+                let coefficients = bspline.coefficients(u);
+                let point = coefficients
+                    .iter()
+                    .zip(&bspline.poles)
+                    .map(|(&n, p)| p.pole * n)
+                    .sum::<Vec3>();
+                assert!(point.distance(bspline.interop(u)) < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn set_pole_checked() {
+        let poles = [Vec3::ZERO, Vec3::X, Vec3::Y];
+        let mut c = curve(&[0., 0., 0., 1., 1., 1.], 2, &poles);
+        let pole = BSplinePole {
+            pole: Vec3::Z,
+            weight: 1.,
+        };
+        c.try_set_pole(1, pole).unwrap();
+        assert_eq!(c.poles()[1].pole, Vec3::Z);
+        assert!(matches!(
+            c.try_set_pole(3, pole),
+            Err(BSplineError::PoleIndexOutOfRange {
+                index: 3,
+                nb_poles: 3
+            })
+        ));
+        let nan = BSplinePole {
+            pole: Vec3::new(Real::NAN, 0., 0.),
+            weight: 1.,
+        };
+        assert!(matches!(
+            c.try_set_pole(0, nan),
+            Err(BSplineError::NonFinitePole { index: 0 })
+        ));
+        c.use_rational = true;
+        let zero_weight = BSplinePole {
+            pole: Vec3::X,
+            weight: 0.,
+        };
+        assert!(matches!(
+            c.try_set_pole(2, zero_weight),
+            Err(BSplineError::NonPositiveWeight { index: 2, .. })
+        ));
+        assert_eq!(c.poles()[2].pole, Vec3::Y);
     }
 }
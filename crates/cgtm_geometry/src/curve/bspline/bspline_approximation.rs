@@ -1,5 +1,6 @@
-use f3l::glam::Vec3;
-use na::{Dyn, OMatrix, RowVector3, U3};
+use std::ops::Range;
+
+use crate::precision::Real;
 
 use crate::curve::bspline::bspline_knots::BSplineKnots;
 
@@ -7,11 +8,15 @@ use super::{
     bspline_data_knots::BSplineDataKnotsAlgo,
     bspline_error::BSplineApproximationError,
     bspline_knots::{BSplineFeature, BSplineKnotsAlgo},
-    bspline_poles::BSplinePole,
-    utils::compute_coefficients,
+    bspline_poles::{BSplinePole, ControlPoint},
+    utils::{basis_functions, find_span, solve_banded_cholesky},
     BSpline,
 };
 
+/// Data points per parallel task when accumulating the normal equations.
+#[cfg(feature = "rayon")]
+const APPROXIMATION_CHUNK: usize = 4096;
+
 #[derive(Debug, Clone)]
 pub struct BSplineApproximation {
     pub degree: usize,
@@ -22,70 +27,177 @@ pub struct BSplineApproximation {
 }
 
 impl BSplineApproximation {
-    pub fn try_approximate(&self, data: &[Vec3]) -> Result<BSpline, BSplineApproximationError> {
+    pub fn try_approximate<P: ControlPoint>(
+        &self,
+        data: &[P],
+    ) -> Result<BSpline<P>, BSplineApproximationError> {
+        let u_vector = self.data_knots_algo.generate(data);
+        self.try_approximate_with_parameters(data, &u_vector)
+    }
+
+    /// Same as [`BSplineApproximation::try_approximate`], with the parameter of each
+    /// point given instead of generated by `data_knots_algo`.
+    ///
+    /// Fails if `u_vector` and `data` differ in length, or if the knots of `knots_algo`
+    /// were not built for `degree` and `nb_poles`.
+    pub fn try_approximate_with_parameters<P: ControlPoint>(
+        &self,
+        data: &[P],
+        u_vector: &[Real],
+    ) -> Result<BSpline<P>, BSplineApproximationError> {
         if let BSplineFeature::Periodic(_) = self.feature {
             return Err(BSplineApproximationError::NotAllowPeriodic);
         }
+        if u_vector.len() != data.len() {
+            return Err(BSplineApproximationError::ParametersNumberNotMatch {
+                expected: data.len(),
+                actual: u_vector.len(),
+            });
+        }
 
         let bspline_knots =
             match BSplineKnots::try_build(self.degree, &self.knots_algo, self.feature) {
                 Ok(knots) => knots,
                 Err(e) => return Err(BSplineApproximationError::BSplineKnotsGeneration(e)),
             };
+        bspline_knots.check_for(self.degree, self.nb_poles)?;
         let knots = bspline_knots.original_flatten();
-        let u_vector = self.data_knots_algo.generate(data);
 
+        if data.len() < self.nb_poles.max(3) {
+            return Err(BSplineApproximationError::NotEnoughData);
+        }
         let h = self.nb_poles - 1;
         let n = data.len() - 1;
+        let p = self.degree;
 
-        let ns = u_vector
-            .iter()
-            .map(|&u| {
-                // only allow non-periodic feature, so n_extend must be 0.
-                compute_coefficients(u, &knots, self.nb_poles, self.degree, 0)
-            })
-            .collect::<Vec<_>>();
-
-        let mut qks = (1..=n - 1)
-            .map(|k| {
-                let coe = &ns[k];
-                data[k] - coe[0] * data[0] - coe[h] * data[n]
-            })
-            .collect::<Vec<_>>();
-        qks.insert(0, Vec3::ZERO);
-
-        let q_rows = (1..=h - 1)
-            .map(|i| {
-                let r = (1..=n - 1).map(|k| ns[k][i] * qks[k]).sum::<Vec3>();
-                RowVector3::new(r.x, r.y, r.z)
-            })
-            .collect::<Vec<_>>();
-        let q_matrix = OMatrix::<f32, Dyn, U3>::from_rows(&q_rows);
-        let mut n_matrix = OMatrix::<f32, Dyn, Dyn>::zeros(n - 1, h - 1);
-        (1..=n - 1).for_each(|k| {
-            (1..=h - 1).for_each(|i| *n_matrix.index_mut((k - 1, i - 1)) = ns[k][i]);
-        });
-        let m_matrix = n_matrix.transpose() * n_matrix;
-        let decompose = m_matrix.svd(true, true);
-        let p_matrix = decompose.solve(&q_matrix, 1e-6).expect("Lu Solve (Ax=B).");
+        // Only the `degree + 1` basis functions of a span are non-zero, so the normal
+        // matrix `N^T N` is banded with bandwidth `degree`.
+        let w = p + 1;
+        let accumulate = |ks: Range<usize>| {
+            let mut band = vec![0.; (h - 1) * w];
+            let mut rhs = vec![P::ZERO; h - 1];
+            ks.for_each(|k| {
+                let u = u_vector[k];
+                let span = find_span(u, &knots, p, self.nb_poles);
+                let first = span - p;
+                let values = basis_functions(span, u, &knots, p);
+                let basis = |i: usize| {
+                    if i >= first && i <= span {
+                        values[i - first]
+                    } else {
+                        0.
+                    }
+                };
+                let qk = data[k] - data[0] * basis(0) - data[n] * basis(h);
+                for (a, &na) in values.iter().enumerate() {
+                    let i = first + a;
+                    if i == 0 || i == h {
+                        continue;
+                    }
+                    rhs[i - 1] += qk * na;
+                    for (b, &nb) in values.iter().enumerate().take(a + 1) {
+                        let j = first + b;
+                        if j != 0 {
+                            band[(i - 1) * w + i - j] += na * nb;
+                        }
+                    }
+                }
+            });
+            (band, rhs)
+        };
+        #[cfg(not(feature = "rayon"))]
+        let (band, rhs) = accumulate(1..n);
+        #[cfg(feature = "rayon")]
+        let (band, rhs) = {
+            use rayon::prelude::*;
+            (1..n)
+                .step_by(APPROXIMATION_CHUNK)
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|start| accumulate(start..(start + APPROXIMATION_CHUNK).min(n)))
+                .reduce(
+                    || (vec![0.; (h - 1) * w], vec![P::ZERO; h - 1]),
+                    |(mut band, mut rhs), (other_band, other_rhs)| {
+                        band.iter_mut().zip(other_band).for_each(|(a, b)| *a += b);
+                        rhs.iter_mut().zip(other_rhs).for_each(|(a, b)| *a += b);
+                        (band, rhs)
+                    },
+                )
+        };
+        let inner = solve_banded_cholesky(band, p, rhs)
+            .ok_or(BSplineApproximationError::SingularNormalEquations)?;
 
         let mut poles = vec![data[0]];
-        for row in p_matrix.row_iter() {
-            poles.push(Vec3::new(row[0], row[1], row[2]));
-        }
+        poles.extend(inner);
         poles.push(data[n]);
 
         let bspline_poles = poles
             .into_iter()
-            .map(|pole| BSplinePole { pole, weight: 1f32 })
+            .map(|pole| BSplinePole { pole, weight: 1. })
             .collect::<Vec<_>>();
 
-        Ok(BSpline {
-            degree: self.degree,
-            is_periodic: false,
-            knots: bspline_knots,
-            poles: bspline_poles,
-            use_rational: false,
-        })
+        Ok(BSpline::try_new(
+            self.degree,
+            bspline_knots,
+            bspline_poles,
+            false,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::bspline_error::BSplineError;
+    use crate::precision::Vec3;
+
+    fn approximation(nb_poles: usize, knots_poles: usize) -> BSplineApproximation {
+        BSplineApproximation {
+            degree: 3,
+            nb_poles,
+            feature: BSplineFeature::ClampAll,
+            knots_algo: BSplineKnotsAlgo::Uniform(knots_poles),
+            data_knots_algo: BSplineDataKnotsAlgo::Uniform,
+        }
+    }
+
+    fn data() -> Vec<Vec3> {
+        (0..20)
+            .map(|i| {
+                let t = i as Real / 19.;
+                Vec3::new(t, t * t * t - t, 0.5 * t * t)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn approximate_cubic_exactly() {
+        let data = data();
+        let us = (0..20).map(|i| i as Real / 19.).collect::<Vec<_>>();
+        let bspline = approximation(6, 6)
+            .try_approximate_with_parameters(&data, &us)
+            .unwrap();
+        for (&u, point) in us.iter().zip(&data) {
+            assert!(bspline.interop(u).distance(*point) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn approximate_rejects_mismatches() {
+        let data = data();
+        let us = (0..20).map(|i| i as Real / 19.).collect::<Vec<_>>();
+        assert!(matches!(
+            approximation(8, 10).try_approximate_with_parameters(&data, &us),
+            Err(BSplineApproximationError::BSplineErr(
+                BSplineError::KnotsNumberNotMatch { .. }
+            ))
+        ));
+        assert!(matches!(
+            approximation(6, 6).try_approximate_with_parameters(&data, &us[..10]),
+            Err(BSplineApproximationError::ParametersNumberNotMatch {
+                expected: 20,
+                actual: 10
+            })
+        ));
     }
 }
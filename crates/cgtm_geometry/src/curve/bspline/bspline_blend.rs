@@ -0,0 +1,231 @@
+use crate::precision::{Real, Vec4};
+
+use super::{
+    bspline_error::BSplineBlendError,
+    bspline_knots::{BSplineKnot, BSplineKnots},
+    bspline_poles::BSplinePole,
+    bspline_refinement::binomial,
+    BSpline,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BSplineContinuity {
+    G0,
+    G1,
+    G2,
+    G3,
+}
+
+impl BSplineContinuity {
+    /// Number of derivatives matched.
+    pub fn order(&self) -> usize {
+        match self {
+            BSplineContinuity::G0 => 0,
+            BSplineContinuity::G1 => 1,
+            BSplineContinuity::G2 => 2,
+            BSplineContinuity::G3 => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BSplineBlend {
+    pub continuity_start: BSplineContinuity,
+    pub continuity_end: BSplineContinuity,
+    /// Tangent length at the start, relative to the distance between the two ends.
+    pub tension_start: Real,
+    /// Tangent length at the end, relative to the distance between the two ends.
+    pub tension_end: Real,
+}
+
+impl Default for BSplineBlend {
+    fn default() -> Self {
+        Self {
+            continuity_start: BSplineContinuity::G1,
+            continuity_end: BSplineContinuity::G1,
+            tension_start: 1.,
+            tension_end: 1.,
+        }
+    }
+}
+
+impl BSplineBlend {
+    /// Bezier curve from the end of `from` to the start of `to`.
+    ///
+    /// The degree is `continuity_start + continuity_end + 1`. Each end matches the
+    /// derivatives of the reparameterized curve `C(alpha * t)`, which gives `G^k`, with
+    /// `alpha` so that the tangent length is `tension * |to.start - from.end|`.
+    /// Derivatives are matched on homogeneous poles, so a rational end gives a rational
+    /// blend with the exact continuity. Tensions should be finite and positive.
+    pub fn try_blend(&self, from: &BSpline, to: &BSpline) -> Result<BSpline, BSplineBlendError> {
+        for tension in [self.tension_start, self.tension_end] {
+            if !(tension.is_finite() && tension > 0.) {
+                return Err(BSplineBlendError::InvalidTension { tension });
+            }
+        }
+        let k_start = self.continuity_start.order();
+        let k_end = self.continuity_end.order();
+        let degree = k_start + k_end + 1;
+
+        let (u_start, u_end) = (from.upper_parameter(), to.lower_parameter());
+        let chord = from.interop(u_start).distance(to.interop(u_end));
+        if chord <= Real::EPSILON {
+            return Err(BSplineBlendError::CoincidentEnds);
+        }
+        let targets_start =
+            Self::scaled_derivatives(from, u_start, k_start, self.tension_start * chord, 1.)?;
+        // Leaving `to` backwards, from the end of the blend.
+        let targets_end =
+            Self::scaled_derivatives(to, u_end, k_end, self.tension_end * chord, -1.)?;
+
+        let start = bezier_poles_from_derivatives(&targets_start, degree);
+        let end = bezier_poles_from_derivatives(&targets_end, degree);
+        let poles = start
+            .into_iter()
+            .chain(end.into_iter().rev())
+            .map(|pw| BSplinePole {
+                pole: pw.truncate() / pw.w,
+                weight: pw.w,
+            })
+            .collect::<Vec<_>>();
+
+        let knots = vec![
+            BSplineKnot {
+                value: 0.,
+                multiplicity: degree + 1,
+            },
+            BSplineKnot {
+                value: 1.,
+                multiplicity: degree + 1,
+            },
+        ];
+        let knots = BSplineKnots::try_new(knots, degree, poles.len(), false)?;
+        let use_rational = from.use_rational || to.use_rational;
+        Ok(BSpline::try_new(degree, knots, poles, use_rational)?)
+    }
+
+    /// Homogeneous derivatives `(sign * alpha)^j Cw^(j)` at `u`, with `|alpha C'| = length`.
+    fn scaled_derivatives(
+        curve: &BSpline,
+        u: Real,
+        order: usize,
+        length: Real,
+        sign: Real,
+    ) -> Result<Vec<Vec4>, BSplineBlendError> {
+        let ders = curve
+            .homogeneous_derivatives(u, order)
+            .into_iter()
+            .map(|d| d.pole.extend(d.weight))
+            .collect::<Vec<_>>();
+        if order == 0 {
+            return Ok(ders);
+        }
+        let speed = curve.derivatives(u, 1)[1].length();
+        if speed <= Real::EPSILON {
+            return Err(BSplineBlendError::DegenerateTangent);
+        }
+        let alpha = sign * length / speed;
+        Ok(ders
+            .iter()
+            .enumerate()
+            .map(|(j, &d)| alpha.powi(j as i32) * d)
+            .collect())
+    }
+}
+
+/// First `ders.len()` poles of a Bezier curve of `degree` with `B^(j)(0) = ders[j]`.
+fn bezier_poles_from_derivatives(ders: &[Vec4], degree: usize) -> Vec<Vec4> {
+    let mut poles: Vec<Vec4> = Vec::with_capacity(ders.len());
+    for (j, &d) in ders.iter().enumerate() {
+        // B^(j)(0) = n! / (n - j)! * sum_i (-1)^(j - i) binom(j, i) P_i
+        let falling = (0..j).map(|i| (degree - i) as Real).product::<Real>();
+        let mut pole = d / falling;
+        for (i, &p) in poles.iter().enumerate() {
+            let sign = if (j - i) % 2 == 0 { 1. } else { -1. };
+            pole -= sign * binomial(j, i) * p;
+        }
+        poles.push(pole);
+    }
+    poles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::Vec3;
+
+    /// Rational quarter circle of `radius` around `center`, from angle `start`.
+    fn arc(center: Vec3, radius: Real, start: Real) -> BSpline {
+        let at = |angle: Real| center + radius * Vec3::new(angle.cos(), angle.sin(), 0.);
+        let quarter = (90. as Real).to_radians();
+        let corner = center
+            + radius
+                * 2.
+                * Vec3::new(
+                    (start + quarter / 2.).cos(),
+                    (start + quarter / 2.).sin(),
+                    0.,
+                )
+                / (2. as Real).sqrt();
+        let weight = (0.5 as Real).sqrt();
+        let poles = vec![
+            BSplinePole {
+                pole: at(start),
+                weight: 1.,
+            },
+            BSplinePole {
+                pole: corner,
+                weight,
+            },
+            BSplinePole {
+                pole: at(start + quarter),
+                weight: 1.,
+            },
+        ];
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 0., 1., 1., 1.], 2, 3, false).unwrap();
+        BSpline::try_new(2, knots, poles, true).unwrap()
+    }
+
+    fn curvature(ders: &[Vec3]) -> Real {
+        ders[1].cross(ders[2]).length() / ders[1].length().powi(3)
+    }
+
+    #[test]
+    fn blend_rational_ends_g2() {
+        let from = arc(Vec3::ZERO, 1., 0.);
+        let to = arc(Vec3::new(-4., 0., 0.), 2., 0.);
+        let blend = BSplineBlend {
+            continuity_start: BSplineContinuity::G2,
+            continuity_end: BSplineContinuity::G2,
+            ..Default::default()
+        };
+        let curve = blend.try_blend(&from, &to).unwrap();
+        assert!(curve.use_rational);
+
+        let (a, b) = (from.derivatives(1., 2), curve.derivatives(0., 2));
+        assert!(a[0].distance(b[0]) < 1e-5);
+        assert!(a[1].normalize().distance(b[1].normalize()) < 1e-4);
+        assert!((curvature(&b) - 1.).abs() < 1e-3);
+
+        let (a, b) = (to.derivatives(0., 2), curve.derivatives(1., 2));
+        assert!(a[0].distance(b[0]) < 1e-5);
+        assert!(a[1].normalize().distance(b[1].normalize()) < 1e-4);
+        assert!((curvature(&b) - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn blend_rejects_invalid_tension() {
+        let from = arc(Vec3::ZERO, 1., 0.);
+        let to = arc(Vec3::new(-4., 0., 0.), 2., 0.);
+        for tension in [0., -1., Real::NAN, Real::INFINITY] {
+            let blend = BSplineBlend {
+                tension_end: tension,
+                ..Default::default()
+            };
+            assert!(matches!(
+                blend.try_blend(&from, &to),
+                Err(BSplineBlendError::InvalidTension { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::precision::{Real, Vec3};
+
+use super::{
+    bspline_error::BSplineError,
+    bspline_knots::{BSplineKnot, BSplineKnots},
+    bspline_poles::{BSplinePole, ControlPoint},
+    BSpline,
+};
+
+#[derive(Debug, Clone)]
+pub struct BSplineBuilder<P = Vec3> {
+    pub degree: usize,
+    pub knots: Vec<BSplineKnot>,
+    pub poles: Vec<P>,
+    /// One per pole, the curve is rational when given.
+    pub weights: Option<Vec<Real>>,
+    pub is_periodic: bool,
+}
+
+impl<P> Default for BSplineBuilder<P> {
+    fn default() -> Self {
+        Self {
+            degree: 3,
+            knots: vec![],
+            poles: vec![],
+            weights: None,
+            is_periodic: false,
+        }
+    }
+}
+
+impl<P: ControlPoint> BSplineBuilder<P> {
+    pub fn try_build(&self) -> Result<BSpline<P>, BSplineError> {
+        let knots = BSplineKnots::try_new(
+            self.knots.clone(),
+            self.degree,
+            self.poles.len(),
+            self.is_periodic,
+        )?;
+        let poles = match &self.weights {
+            Some(weights) => {
+                if weights.len() != self.poles.len() {
+                    return Err(BSplineError::WeightsNumberNotMatch {
+                        expected: self.poles.len(),
+                        actual: weights.len(),
+                    });
+                }
+                self.poles
+                    .iter()
+                    .zip(weights)
+                    .map(|(&pole, &weight)| BSplinePole { pole, weight })
+                    .collect()
+            }
+            None => self
+                .poles
+                .iter()
+                .map(|&pole| BSplinePole { pole, weight: 1. })
+                .collect(),
+        };
+        BSpline::try_new(self.degree, knots, poles, self.weights.is_some())
+    }
+}
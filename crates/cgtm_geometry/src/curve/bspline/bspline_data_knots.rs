@@ -1,36 +1,38 @@
-use f3l::glam::Vec3;
+use crate::precision::Real;
+
+use super::bspline_poles::ControlPoint;
 
 #[derive(Debug, Clone, Copy, Default)]
 pub enum BSplineDataKnotsAlgo {
     #[default]
     Uniform,
     ChordLength,
-    Centripetal(f32),
+    Centripetal(Real),
 }
 
 impl BSplineDataKnotsAlgo {
-    pub const CENTRIPETAL_SQRT: f32 = 0.5;
+    pub const CENTRIPETAL_SQRT: Real = 0.5;
 
-    pub fn generate(&self, data: &[Vec3]) -> Vec<f32> {
+    pub fn generate<P: ControlPoint>(&self, data: &[P]) -> Vec<Real> {
         match self {
             BSplineDataKnotsAlgo::Uniform => uniform(data),
-            BSplineDataKnotsAlgo::ChordLength => centripetal(data, 1f32),
+            BSplineDataKnotsAlgo::ChordLength => centripetal(data, 1.),
             BSplineDataKnotsAlgo::Centripetal(alpha) => centripetal(data, *alpha),
         }
     }
 }
 
-fn uniform(data: &[Vec3]) -> Vec<f32> {
-    let n = 1f32 / data.len() as f32;
-    (0..data.len()).map(|i| (i + 1) as f32 * n).collect()
+fn uniform<P>(data: &[P]) -> Vec<Real> {
+    let n = 1. / data.len() as Real;
+    (0..data.len()).map(|i| (i + 1) as Real * n).collect()
 }
 
-fn centripetal(data: &[Vec3], alpha: f32) -> Vec<f32> {
+fn centripetal<P: ControlPoint>(data: &[P], alpha: Real) -> Vec<Real> {
     let (mut acc, sum) = data.iter().enumerate().fold(
-        (Vec::<f32>::with_capacity(data.len()), 0f32),
+        (Vec::<Real>::with_capacity(data.len()), 0.),
         |(mut acc, mut sum), (i, &v)| {
             if i == 0 {
-                acc.push(0f32);
+                acc.push(0.);
             } else {
                 let d = (v.distance(data[i - 1])).powf(alpha);
                 acc.push(acc.last().unwrap() + d);
@@ -0,0 +1,102 @@
+use crate::precision::Real;
+
+use super::{bspline_error::BSplineError, bspline_poles::ControlPoint, BSpline};
+
+/// Parameter interval `[lower, upper]` of a curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BSplineDomain {
+    pub lower: Real,
+    pub upper: Real,
+}
+
+impl BSplineDomain {
+    pub fn length(&self) -> Real {
+        self.upper - self.lower
+    }
+
+    pub fn contains(&self, u: Real) -> bool {
+        self.lower <= u && u <= self.upper
+    }
+
+    pub fn clamp(&self, u: Real) -> Real {
+        u.clamp(self.lower, self.upper)
+    }
+
+    /// `u` shifted by a multiple of the length into `[lower, upper)`.
+    pub fn wrap(&self, u: Real) -> Real {
+        self.lower + (u - self.lower).rem_euclid(self.length())
+    }
+}
+
+/// How parameters outside the domain are evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BSplineExtrapolation {
+    /// Fails with [`BSplineError::OutOfDomain`].
+    #[default]
+    Error,
+    /// Evaluates at the nearest end.
+    Clamp,
+    /// Shifts by the period, only for periodic curves.
+    Wrap,
+    /// Extends the polynomial of the first or last span, also for periodic curves.
+    Polynomial,
+}
+
+impl<P: ControlPoint> BSpline<P> {
+    pub fn domain(&self) -> BSplineDomain {
+        BSplineDomain {
+            lower: self.lower_parameter(),
+            upper: self.upper_parameter(),
+        }
+    }
+
+    /// Parameter evaluated for `u` with `extrapolation`.
+    pub fn try_resolve_parameter(
+        &self,
+        u: Real,
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<Real, BSplineError> {
+        let domain = self.domain();
+        if !u.is_finite() {
+            return Err(BSplineError::OutOfDomain {
+                u,
+                lower: domain.lower,
+                upper: domain.upper,
+            });
+        }
+        if domain.contains(u) {
+            return Ok(u);
+        }
+        match extrapolation {
+            BSplineExtrapolation::Error => Err(BSplineError::OutOfDomain {
+                u,
+                lower: domain.lower,
+                upper: domain.upper,
+            }),
+            BSplineExtrapolation::Clamp => Ok(domain.clamp(u)),
+            BSplineExtrapolation::Wrap if self.is_periodic => Ok(domain.wrap(u)),
+            BSplineExtrapolation::Wrap => Err(BSplineError::NotPeriodic),
+            BSplineExtrapolation::Polynomial => Ok(u),
+        }
+    }
+
+    /// Point `C(u)`, parameters outside the domain follow `extrapolation`.
+    pub fn try_evaluate(
+        &self,
+        u: Real,
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<P, BSplineError> {
+        Ok(self.interop_extended(self.try_resolve_parameter(u, extrapolation)?))
+    }
+
+    /// Same as [`BSpline::derivatives`], parameters outside the domain follow
+    /// `extrapolation`.
+    pub fn try_derivatives(
+        &self,
+        u: Real,
+        order: usize,
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<Vec<P>, BSplineError> {
+        Ok(self.derivatives_extended(self.try_resolve_parameter(u, extrapolation)?, order))
+    }
+}
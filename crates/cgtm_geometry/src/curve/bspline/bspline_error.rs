@@ -1,17 +1,61 @@
 use thiserror::Error;
 
+use crate::precision::Real;
+
 #[derive(Debug, Clone, Copy, Error)]
 pub enum BSplineError {
-    #[error("Knots should be greater than 2.")]
-    TooFewKnots,
-    #[error("Poles should be greater than 2.")]
-    TooFewPoles,
-    #[error("Multiplicity should be less or equal `degree`. If non-periodic, first and last could be `degree+1`.")]
-    MultiplicityOverDegree,
-    #[error("When periodic, multiplicity of first and last should be equal.")]
-    PeriodicEdgeNotMatch,
-    #[error("When non-periodic, knots number should be poles number + degree + 1. When periodic, knots number - last multiplicity = poles number.")]
-    KnotsNumberNotMatch,
+    #[error("Knots should be at least 2, got {0}.")]
+    TooFewKnots(usize),
+    #[error("Degree {degree} needs at least {min} poles, got {nb_poles}.")]
+    TooFewPoles {
+        degree: usize,
+        nb_poles: usize,
+        min: usize,
+    },
+    #[error("Knot {index} has multiplicity {multiplicity}, at most {max} allowed with degree {degree}. If non-periodic, first and last could be `degree+1`.")]
+    MultiplicityOverDegree {
+        index: usize,
+        multiplicity: usize,
+        max: usize,
+        degree: usize,
+    },
+    #[error("Knot {index} value {value} should be greater than previous value {previous}.")]
+    NonIncreasingKnots {
+        index: usize,
+        value: Real,
+        previous: Real,
+    },
+    #[error("Knot {index} value {value} is not finite.")]
+    NonFiniteKnot { index: usize, value: Real },
+    #[error("When periodic, multiplicity of first ({first}) and last ({last}) should be equal.")]
+    PeriodicEdgeNotMatch { first: usize, last: usize },
+    #[error("Degree {degree} with {nb_poles} poles needs a total multiplicity of {expected}, got {actual}. When non-periodic, knots number should be poles number + degree + 1. When periodic, knots number - last multiplicity = poles number.")]
+    KnotsNumberNotMatch {
+        degree: usize,
+        nb_poles: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Domain lower bound {lower} should be less than upper bound {upper}.")]
+    EmptyDomain { lower: Real, upper: Real },
+    #[error("Knots were not built for degree {degree}.")]
+    DegreeNotMatch { degree: usize },
+    #[error("Weights number should be poles number {expected}, got {actual}.")]
+    WeightsNumberNotMatch { expected: usize, actual: usize },
+    #[error("Pole {index} has weight {weight}, weights should be finite and positive.")]
+    NonPositiveWeight { index: usize, weight: Real },
+    #[error("Pole {index} has a non-finite coordinate.")]
+    NonFinitePole { index: usize },
+    #[error("Pole index {index} is out of range, the curve has {nb_poles} poles.")]
+    PoleIndexOutOfRange { index: usize, nb_poles: usize },
+    #[error("Parameter {u} is outside the domain [{lower}, {upper}].")]
+    OutOfDomain { u: Real, lower: Real, upper: Real },
+    #[error("Only periodic curves can wrap parameters.")]
+    NotPeriodic,
+    #[error("Rotation axis should be finite and non-zero.")]
+    ZeroAxis,
+    #[error("Mirror normal should be finite and non-zero.")]
+    ZeroNormal,
 }
 
 #[derive(Debug, Clone, Copy, Error)]
@@ -22,10 +66,72 @@ pub enum BSplineKnotsGenError {
     TooFewPoles,
 }
 
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineInterpolationError {
+    #[error(transparent)]
+    BSpline(#[from] BSplineError),
+    #[error("Degree {degree} needs more than {degree} conditions, got {nb_conditions}.")]
+    NotEnoughData { degree: usize, nb_conditions: usize },
+    #[error("Points number should be parameters number {expected}, got {actual}.")]
+    PointsNumberNotMatch { expected: usize, actual: usize },
+    #[error("Parameter {index} should be finite and greater than the previous one.")]
+    NonIncreasingParameters { index: usize },
+    #[error("End derivatives do not match the conditions of the system.")]
+    DerivativesNotMatch,
+    #[error("Interpolation system is singular.")]
+    SingularSystem,
+}
+
 #[derive(Debug, Clone, Copy, Error)]
 pub enum BSplineApproximationError {
+    #[error(transparent)]
+    BSplineErr(#[from] BSplineError),
     #[error(transparent)]
     BSplineKnotsGeneration(#[from] BSplineKnotsGenError),
     #[error("Not allow to approximate periodic bspline.")]
     NotAllowPeriodic,
+    #[error("Data should have at least as many points as poles, and at least 3.")]
+    NotEnoughData,
+    #[error("Normal equations are singular, some poles have no data in their support.")]
+    SingularNormalEquations,
+    #[error("Parameters number should be data number {expected}, got {actual}.")]
+    ParametersNumberNotMatch { expected: usize, actual: usize },
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineOffsetError {
+    #[error(transparent)]
+    Approximation(#[from] BSplineApproximationError),
+    #[error(transparent)]
+    Join(#[from] BSplineJoinError),
+    #[error("Curve is not planar in the given plane.")]
+    NotPlanar,
+    #[error("Tangent vanishes or is parallel to the plane normal.")]
+    DegenerateTangent,
+    #[error("Offset is empty after trimming loops.")]
+    EmptyOffset,
+    #[error("Could not approximate the offset within tolerance.")]
+    ToleranceNotReached,
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineJoinError {
+    #[error("No curves to join.")]
+    NoCurves,
+    #[error("Curve {0} does not connect to the previous one within tolerance.")]
+    NotConnected(usize),
+    #[error(transparent)]
+    BSplineErr(#[from] BSplineError),
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineBlendError {
+    #[error(transparent)]
+    BSplineErr(#[from] BSplineError),
+    #[error("Curve ends to blend are coincident.")]
+    CoincidentEnds,
+    #[error("Tangent vanishes at a curve end.")]
+    DegenerateTangent,
+    #[error("Tension {tension} should be finite and positive.")]
+    InvalidTension { tension: Real },
 }
@@ -0,0 +1,237 @@
+use crate::precision::{Real, Vec3};
+
+use super::{
+    bspline_domain::BSplineExtrapolation,
+    bspline_error::BSplineError,
+    bspline_poles::{BSplinePole, ControlPoint},
+    utils::de_boor_in_place,
+    BSpline,
+};
+
+/// Parameters per parallel task, see [`BSpline::evaluate_many`].
+#[cfg(feature = "rayon")]
+const EVALUATION_CHUNK: usize = 1024;
+
+/// Repeated evaluation of a curve without allocating.
+///
+/// The knots and first pole of each span are located once, and the de Boor working
+/// space is reused between calls.
+#[derive(Debug, Clone)]
+pub struct BSplineEvaluator<'a, P = Vec3> {
+    bspline: &'a BSpline<P>,
+    /// Upper parameter of each span, increasing.
+    bounds: Vec<Real>,
+    knot_slices: Vec<&'a [Real]>,
+    pole_indices: Vec<usize>,
+    scratch: Vec<BSplinePole<P>>,
+    last_span: usize,
+}
+
+impl<'a, P: ControlPoint> BSplineEvaluator<'a, P> {
+    pub fn new(bspline: &'a BSpline<P>) -> Self {
+        let knots = &bspline.knots;
+        let spans = knots.lower..knots.upper;
+        Self {
+            bspline,
+            bounds: spans.clone().map(|k| knots.knots[k + 1].value).collect(),
+            knot_slices: spans
+                .clone()
+                .map(|k| knots.get_knots_bounds_slice(k, bspline.degree, bspline.is_periodic))
+                .collect(),
+            pole_indices: spans.map(|k| bspline.pole_index(k)).collect(),
+            scratch: vec![BSplinePole::default(); bspline.degree + 1],
+            last_span: 0,
+        }
+    }
+
+    /// Point `C(u)`, same as [`BSpline::interop`].
+    ///
+    /// The span of the previous call is tried first, so nearby parameters are cheapest.
+    pub fn evaluate(&mut self, u: Real) -> P {
+        self.evaluate_extended(self.bspline.periodic_parameter(u))
+    }
+
+    /// Point `C(u)`, same as [`BSpline::try_evaluate`].
+    pub fn try_evaluate(
+        &mut self,
+        u: Real,
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<P, BSplineError> {
+        let u = self.bspline.try_resolve_parameter(u, extrapolation)?;
+        Ok(self.evaluate_extended(u))
+    }
+
+    fn evaluate_extended(&mut self, u: Real) -> P {
+        let span = self.span(u);
+        self.last_span = span;
+        self.bspline
+            .fill_poles(self.pole_indices[span], &mut self.scratch);
+        de_boor_in_place(
+            u,
+            self.knot_slices[span],
+            &mut self.scratch,
+            self.bspline.degree,
+            self.bspline.use_rational,
+        )
+    }
+
+    /// Span with `bounds[span - 1] < u <= bounds[span]`, the first and last are open.
+    fn span(&self, u: Real) -> usize {
+        let last = self.bounds.len() - 1;
+        let contains = |span: usize| {
+            (span == 0 || u > self.bounds[span - 1]) && (span == last || u <= self.bounds[span])
+        };
+        if contains(self.last_span) {
+            return self.last_span;
+        }
+        self.bounds.partition_point(|&b| b < u).min(last)
+    }
+}
+
+impl<P: ControlPoint> BSpline<P> {
+    /// Evaluator caching the span structure, see [`BSplineEvaluator`].
+    pub fn evaluator(&self) -> BSplineEvaluator<'_, P> {
+        BSplineEvaluator::new(self)
+    }
+
+    /// Points `C(u)` for each parameter of `us`, outside the domain like
+    /// [`BSpline::interop`].
+    ///
+    /// The span of the previous parameter is reused while it contains the next one, so
+    /// sorted parameters are cheapest. With the `rayon` feature, chunks of parameters are
+    /// evaluated in parallel.
+    pub fn evaluate_many(&self, us: &[Real]) -> Vec<P> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            us.par_chunks(EVALUATION_CHUNK)
+                .map(|chunk| self.evaluate_sequence(chunk))
+                .collect::<Vec<_>>()
+                .concat()
+        }
+        #[cfg(not(feature = "rayon"))]
+        self.evaluate_sequence(us)
+    }
+
+    /// Same as [`BSpline::evaluate_many`], parameters outside the domain follow
+    /// `extrapolation`.
+    pub fn try_evaluate_many(
+        &self,
+        us: &[Real],
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<Vec<P>, BSplineError> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            Ok(us
+                .par_chunks(EVALUATION_CHUNK)
+                .map(|chunk| self.try_evaluate_sequence(chunk, extrapolation))
+                .collect::<Result<Vec<_>, _>>()?
+                .concat())
+        }
+        #[cfg(not(feature = "rayon"))]
+        self.try_evaluate_sequence(us, extrapolation)
+    }
+
+    fn evaluate_sequence(&self, us: &[Real]) -> Vec<P> {
+        let mut evaluator = self.evaluator();
+        us.iter().map(|&u| evaluator.evaluate(u)).collect()
+    }
+
+    fn try_evaluate_sequence(
+        &self,
+        us: &[Real],
+        extrapolation: BSplineExtrapolation,
+    ) -> Result<Vec<P>, BSplineError> {
+        let mut evaluator = self.evaluator();
+        us.iter()
+            .map(|&u| evaluator.try_evaluate(u, extrapolation))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{bspline_knots::BSplineKnots, utils::de_boor};
+
+    #[test]
+    fn evaluation_past_upper_extends_last_span() {
+        let flatten = [0., 0., 0., 0.4, 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 2, 4, false).unwrap();
+        let poles = [Vec3::ZERO, Vec3::Y, Vec3::new(2., 1., 0.), Vec3::X * 3.]
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .to_vec();
+        let bspline = BSpline::try_new(2, knots, poles.clone(), false).unwrap();
+
+        assert_eq!(bspline.interop(1.), Vec3::X * 3.);
+        let us = [1.2, 1.5, -0.3];
+        let points = bspline.evaluate_many(&us);
+        for (&u, point) in us.iter().zip(points) {
+            // Polynomial of the first or last span, its knots and poles.
+            let expected = if u > 1. {
+                de_boor(u, &flatten[2..6], &poles[1..], 2, false)
+            } else {
+                de_boor(u, &flatten[1..5], &poles[..3], 2, false)
+            };
+            assert!(bspline.interop(u).distance(expected) < 1e-5);
+            assert!(point.distance(expected) < 1e-5);
+        }
+    }
+
+    fn periodic() -> BSpline {
+        let knots = BSplineKnots::try_from_flatten(&[0., 1., 2., 3., 4.], 2, 4, true).unwrap();
+        let poles = [Vec3::ZERO, Vec3::Y, Vec3::new(2., 1., 0.), Vec3::X * 3.]
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .to_vec();
+        BSpline::try_new(2, knots, poles, false).unwrap()
+    }
+
+    #[test]
+    fn periodic_evaluation_wraps() {
+        let bspline = periodic();
+        let us = [-3.5, -0.25, 4.5, 6.75, 9.];
+        let points = bspline.evaluate_many(&us);
+        for (&u, point) in us.iter().zip(points) {
+            let expected = bspline.interop(bspline.domain().wrap(u));
+            assert!(bspline.interop(u).distance(expected) < 1e-5);
+            assert!(point.distance(expected) < 1e-5);
+            let ders = bspline.derivatives(u, 1);
+            let wrapped = bspline.derivatives(bspline.domain().wrap(u), 1);
+            assert!(ders[1].distance(wrapped[1]) < 1e-4);
+        }
+        // Polynomial extrapolation still extends the last span.
+        let extended = bspline
+            .try_evaluate(4.5, BSplineExtrapolation::Polynomial)
+            .unwrap();
+        assert!(extended.distance(bspline.interop(0.5)) > 1e-2);
+    }
+
+    #[test]
+    fn evaluation_follows_extrapolation() {
+        let bspline = periodic();
+        let us = [0.5, 5.5];
+        assert!(matches!(
+            bspline.try_evaluate_many(&us, BSplineExtrapolation::Error),
+            Err(BSplineError::OutOfDomain { .. })
+        ));
+        let clamped = bspline
+            .try_evaluate_many(&us, BSplineExtrapolation::Clamp)
+            .unwrap();
+        assert!(clamped[1].distance(bspline.interop(4.)) < 1e-5);
+        let wrapped = bspline
+            .try_evaluate_many(&us, BSplineExtrapolation::Wrap)
+            .unwrap();
+        assert!(wrapped[1].distance(bspline.interop(1.5)) < 1e-5);
+        let mut evaluator = bspline.evaluator();
+        for &u in &us {
+            let expected = bspline
+                .try_evaluate(u, BSplineExtrapolation::Polynomial)
+                .unwrap();
+            let point = evaluator
+                .try_evaluate(u, BSplineExtrapolation::Polynomial)
+                .unwrap();
+            assert!(point.distance(expected) < 1e-5);
+        }
+    }
+}
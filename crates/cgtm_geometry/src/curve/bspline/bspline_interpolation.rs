@@ -0,0 +1,322 @@
+use na::{DMatrix, Dyn, LU};
+
+use crate::precision::Real;
+
+use super::{
+    bspline_data_knots::BSplineDataKnotsAlgo,
+    bspline_error::BSplineInterpolationError,
+    bspline_knots::BSplineKnots,
+    bspline_poles::{BSplinePole, ControlPoint},
+    utils::{basis_derivatives, basis_functions, find_span},
+    BSpline,
+};
+
+/// Global interpolation of points by a clamped curve, The NURBS Book 9.2.1 and 9.2.2.
+///
+/// Optional end derivatives add one pole each. Knots average the data parameters, an
+/// end parameter counted twice when its derivative is given.
+#[derive(Debug, Clone)]
+pub struct BSplineInterpolation {
+    pub degree: usize,
+    pub data_knots_algo: BSplineDataKnotsAlgo,
+}
+
+impl BSplineInterpolation {
+    pub fn try_interpolate<P: ControlPoint>(
+        &self,
+        data: &[P],
+    ) -> Result<BSpline<P>, BSplineInterpolationError> {
+        self.try_interpolate_with_derivatives(data, None, None)
+    }
+
+    /// Interpolates `data`, with the first derivative at the first and last points when
+    /// given. Parameters generated by `data_knots_algo` are mapped to `[0, 1]`.
+    pub fn try_interpolate_with_derivatives<P: ControlPoint>(
+        &self,
+        data: &[P],
+        start: Option<P>,
+        end: Option<P>,
+    ) -> Result<BSpline<P>, BSplineInterpolationError> {
+        let u_vector = self.normalized_parameters(data);
+        self.try_interpolate_with_parameters(data, &u_vector, start, end)
+    }
+
+    /// Same as [`BSplineInterpolation::try_interpolate_with_derivatives`], with the
+    /// parameter of each point given. The domain is `[u_vector[0], u_vector[last]]`.
+    pub fn try_interpolate_with_parameters<P: ControlPoint>(
+        &self,
+        data: &[P],
+        u_vector: &[Real],
+        start: Option<P>,
+        end: Option<P>,
+    ) -> Result<BSpline<P>, BSplineInterpolationError> {
+        let system =
+            InterpolationSystem::try_new(self.degree, u_vector, start.is_some(), end.is_some())?;
+        let poles = system.solve(data, start, end)?;
+        let poles = poles
+            .into_iter()
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .collect();
+        Ok(BSpline::try_new(self.degree, system.knots, poles, false)?)
+    }
+
+    /// Periodic curve through `data` and back to its first point, which should not be
+    /// repeated at the end. Parameters include the closing segment and are mapped to
+    /// `[0, 1]`, the domain is one period starting at the first knot.
+    pub fn try_interpolate_closed<P: ControlPoint>(
+        &self,
+        data: &[P],
+    ) -> Result<BSpline<P>, BSplineInterpolationError> {
+        let u_vector = self.normalized_closed_parameters(data);
+        let system = InterpolationSystem::try_new_periodic(self.degree, &u_vector)?;
+        let poles = system.solve(data, None, None)?;
+        let poles = poles
+            .into_iter()
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .collect();
+        Ok(BSpline::try_new(self.degree, system.knots, poles, false)?)
+    }
+
+    /// Parameters of `data` followed by its first point, mapped to `[0, 1]`.
+    pub(crate) fn normalized_closed_parameters<P: ControlPoint>(&self, data: &[P]) -> Vec<Real> {
+        let closed = data.iter().chain(data.first()).copied().collect::<Vec<_>>();
+        self.normalized_parameters(&closed)
+    }
+
+    /// Parameters of `data` by `data_knots_algo`, mapped to `[0, 1]`.
+    pub(crate) fn normalized_parameters<P: ControlPoint>(&self, data: &[P]) -> Vec<Real> {
+        let mut u_vector = self.data_knots_algo.generate(data);
+        if let (Some(&first), Some(&last)) = (u_vector.first(), u_vector.last()) {
+            u_vector
+                .iter_mut()
+                .for_each(|u| *u = (*u - first) / (last - first));
+        }
+        u_vector
+    }
+}
+
+/// Factorized collocation matrix of an interpolation, shared by every sequence of
+/// points with the same parameters and end conditions.
+pub(crate) struct InterpolationSystem {
+    pub(crate) knots: BSplineKnots,
+    lu: LU<Real, Dyn, Dyn>,
+    nb_points: usize,
+    start: bool,
+    end: bool,
+}
+
+impl InterpolationSystem {
+    pub(crate) fn try_new(
+        degree: usize,
+        u_vector: &[Real],
+        start: bool,
+        end: bool,
+    ) -> Result<Self, BSplineInterpolationError> {
+        check_parameters(u_vector)?;
+        // Parameters of the conditions, an end counted twice with its derivative.
+        let mut params = Vec::with_capacity(u_vector.len() + 2);
+        for (k, &u) in u_vector.iter().enumerate() {
+            if k == u_vector.len() - 1 && end {
+                params.push(u);
+            }
+            params.push(u);
+            if k == 0 && start {
+                params.push(u);
+            }
+        }
+        let nb_poles = params.len();
+        if degree == 0 || u_vector.len() < 2 || nb_poles <= degree {
+            return Err(BSplineInterpolationError::NotEnoughData {
+                degree,
+                nb_conditions: nb_poles,
+            });
+        }
+
+        let (first, last) = (params[0], params[nb_poles - 1]);
+        let mut flatten = vec![first; degree + 1];
+        flatten.extend(
+            (1..nb_poles - degree)
+                .map(|j| params[j..j + degree].iter().sum::<Real>() / degree as Real),
+        );
+        flatten.extend(vec![last; degree + 1]);
+        let knots = BSplineKnots::try_from_flatten(&flatten, degree, nb_poles, false)?;
+
+        let mut matrix = DMatrix::<Real>::zeros(nb_poles, nb_poles);
+        for (row, &u) in params.iter().enumerate() {
+            let derivative = (start && row == 1) || (end && row == nb_poles - 2);
+            let span = find_span(u, &flatten, degree, nb_poles);
+            let ders = basis_derivatives(span, u, &flatten, degree, derivative as usize);
+            for (j, &value) in ders[derivative as usize].iter().enumerate() {
+                matrix[(row, span - degree + j)] = value;
+            }
+        }
+        Ok(Self {
+            knots,
+            lu: matrix.lu(),
+            nb_points: u_vector.len(),
+            start,
+            end,
+        })
+    }
+
+    /// Poles interpolating `data` and the end derivatives, which should be given exactly
+    /// when the system was built with them.
+    pub(crate) fn solve<P: ControlPoint>(
+        &self,
+        data: &[P],
+        start: Option<P>,
+        end: Option<P>,
+    ) -> Result<Vec<P>, BSplineInterpolationError> {
+        if data.len() != self.nb_points {
+            return Err(BSplineInterpolationError::PointsNumberNotMatch {
+                expected: self.nb_points,
+                actual: data.len(),
+            });
+        }
+        if start.is_some() != self.start || end.is_some() != self.end {
+            return Err(BSplineInterpolationError::DerivativesNotMatch);
+        }
+        let mut conditions = Vec::with_capacity(data.len() + 2);
+        conditions.push(data[0]);
+        conditions.extend(start);
+        conditions.extend_from_slice(&data[1..data.len() - 1]);
+        conditions.extend(end);
+        conditions.push(data[data.len() - 1]);
+
+        let rhs = DMatrix::from_fn(conditions.len(), P::DIM, |i, k| conditions[i].coord(k));
+        let solution = self
+            .lu
+            .solve(&rhs)
+            .filter(|s| s.iter().all(|c| c.is_finite()))
+            .ok_or(BSplineInterpolationError::SingularSystem)?;
+        Ok((0..conditions.len())
+            .map(|i| {
+                let coords = (0..P::DIM).map(|k| solution[(i, k)]).collect::<Vec<_>>();
+                P::from_coords(&coords)
+            })
+            .collect())
+    }
+}
+
+impl InterpolationSystem {
+    /// System of a periodic interpolation, `u_vector` ends with the parameter closing
+    /// the loop, which has no point of its own.
+    ///
+    /// Knots are the parameters for odd degrees and their midpoints for even ones, so
+    /// the cyclic collocation matrix is not singular.
+    pub(crate) fn try_new_periodic(
+        degree: usize,
+        u_vector: &[Real],
+    ) -> Result<Self, BSplineInterpolationError> {
+        check_parameters(u_vector)?;
+        let nb_poles = u_vector.len().saturating_sub(1);
+        if degree == 0 || nb_poles <= degree {
+            return Err(BSplineInterpolationError::NotEnoughData {
+                degree,
+                nb_conditions: nb_poles,
+            });
+        }
+        let period = u_vector[nb_poles] - u_vector[0];
+        let flatten = if degree % 2 == 1 {
+            u_vector.to_vec()
+        } else {
+            let mut middles = u_vector
+                .windows(2)
+                .map(|w| (w[0] + w[1]) / 2.)
+                .collect::<Vec<_>>();
+            middles.push(middles[0] + period);
+            middles
+        };
+        let knots = BSplineKnots::try_from_flatten(&flatten, degree, nb_poles, true)?;
+
+        let (lower, upper) = (knots.lower_value(), knots.upper_value());
+        let mut matrix = DMatrix::<Real>::zeros(nb_poles, nb_poles);
+        for (row, &u) in u_vector[..nb_poles].iter().enumerate() {
+            let u = if u < lower { u + period } else { u.min(upper) };
+            let span = knots.get_span_index(u);
+            let first = knots.get_pole_index(span, degree, true);
+            let local = knots.get_knots_bounds_slice(span, degree, true);
+            for (j, value) in basis_functions(degree - 1, u, local, degree)
+                .into_iter()
+                .enumerate()
+            {
+                matrix[(row, (first + j) % nb_poles)] += value;
+            }
+        }
+        Ok(Self {
+            knots,
+            lu: matrix.lu(),
+            nb_points: nb_poles,
+            start: false,
+            end: false,
+        })
+    }
+}
+
+fn check_parameters(u_vector: &[Real]) -> Result<(), BSplineInterpolationError> {
+    match (1..u_vector.len()).find(|&i| {
+        !(u_vector[i].is_finite() && u_vector[i - 1].is_finite()) || u_vector[i] <= u_vector[i - 1]
+    }) {
+        Some(index) => Err(BSplineInterpolationError::NonIncreasingParameters { index }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::Vec3;
+
+    /// Points around a bumpy closed loop, unevenly spaced.
+    fn loop_points() -> Vec<Vec3> {
+        (0..9)
+            .map(|k| {
+                let a = 0.7 * k as Real + 0.1 * (k * k) as Real;
+                let r = 2. + 0.3 * (3. * a).sin();
+                Vec3::new(r * a.cos(), r * a.sin(), 0.2 * (2. * a).cos())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn closed_interpolation_is_periodic() {
+        let data = loop_points();
+        for degree in [2, 3, 4] {
+            for data_knots_algo in [
+                BSplineDataKnotsAlgo::Uniform,
+                BSplineDataKnotsAlgo::ChordLength,
+            ] {
+                let interpolation = BSplineInterpolation {
+                    degree,
+                    data_knots_algo,
+                };
+                let curve = interpolation.try_interpolate_closed(&data).unwrap();
+                assert!(curve.is_periodic());
+                let u_vector = interpolation.normalized_closed_parameters(&data);
+                for (&u, &point) in u_vector.iter().zip(&data) {
+                    assert!(curve.interop(u).distance(point) < 1e-4);
+                }
+                assert!(curve.interop(1.).distance(data[0]) < 1e-4);
+                // Both sides of the seam, `C^(degree - 1)` at simple knots, so C2 from cubics.
+                let domain = curve.domain();
+                let before = curve.derivatives_extended(domain.upper, degree - 1);
+                let after = curve.derivatives_extended(domain.lower, degree - 1);
+                for (b, a) in before.iter().zip(&after) {
+                    assert!(b.distance(*a) < 1e-3 * (1. + a.length()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn closed_interpolation_needs_more_points_than_degree() {
+        let interpolation = BSplineInterpolation {
+            degree: 3,
+            data_knots_algo: BSplineDataKnotsAlgo::Uniform,
+        };
+        assert!(matches!(
+            interpolation.try_interpolate_closed(&loop_points()[..3]),
+            Err(BSplineInterpolationError::NotEnoughData { .. })
+        ));
+    }
+}
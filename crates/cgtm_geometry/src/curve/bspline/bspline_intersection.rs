@@ -0,0 +1,191 @@
+use crate::precision::Real;
+
+use super::{
+    utils::{closest_on_curves, closest_on_segments},
+    BSpline,
+};
+
+impl BSpline {
+    /// Number of polyline segments sampled inside each knot span.
+    pub const INTERSECTION_SAMPLES_PER_SPAN: usize = 16;
+
+    const INTERSECTION_MAX_ITERATIONS: usize = 32;
+
+    /// Pairs of parameters `(s, t)` with `s < t` where the curve crosses itself,
+    /// i.e. where `|C(s) - C(t)| <= tolerance`.
+    ///
+    /// The curve is sampled into a polyline, close non-adjacent segments are taken
+    /// as candidates and refined by Gauss-Newton on `C(s) - C(t)`. For periodic
+    /// curves the polyline is closed, so loops across the seam are found as well.
+    pub fn self_intersections(&self, tolerance: Real) -> Vec<(Real, Real)> {
+        let params = self.intersection_samples();
+        let pts = params.iter().map(|&u| self.interop(u)).collect::<Vec<_>>();
+        let nb_segments = pts.len() - 1;
+        let lengths = pts
+            .windows(2)
+            .map(|w| w[0].distance(w[1]))
+            .collect::<Vec<_>>();
+
+        let closed = self.is_periodic || pts[0].distance(pts[nb_segments]) <= tolerance;
+
+        let mut found: Vec<(Real, Real)> = vec![];
+        for i in 0..nb_segments {
+            for j in i + 2..nb_segments {
+                // First and last segments are joined at the seam of a closed curve.
+                if closed && i == 0 && j == nb_segments - 1 {
+                    continue;
+                }
+                let (a, b, dist) = closest_on_segments(pts[i], pts[i + 1], pts[j], pts[j + 1]);
+                if dist > tolerance + 0.25 * (lengths[i] + lengths[j]) {
+                    continue;
+                }
+                let s0 = params[i] + a * (params[i + 1] - params[i]);
+                let t0 = params[j] + b * (params[j + 1] - params[j]);
+                let Some((s, t)) = self.refine_intersection(s0, t0, tolerance) else {
+                    continue;
+                };
+                let (s, t) = if s < t { (s, t) } else { (t, s) };
+                // Converged onto the trivial solution `s == t`.
+                let min_gap = (params[i + 1] - params[i]).min(params[j + 1] - params[j]);
+                if self.parameter_gap(s, t) < min_gap {
+                    continue;
+                }
+                // Around the seam the same crossing can come back with `s` and `t` swapped.
+                let near = |a, b| self.parameter_gap(a, b) < min_gap;
+                let duplicated = found
+                    .iter()
+                    .any(|&(fs, ft)| near(fs, s) && near(ft, t) || near(fs, t) && near(ft, s));
+                if !duplicated {
+                    found.push((s, t));
+                }
+            }
+        }
+        found.sort_by(|a, b| a.0.total_cmp(&b.0));
+        found
+    }
+
+    fn intersection_samples(&self) -> Vec<Real> {
+        let knots = &self.knots.knots[self.knots.lower..=self.knots.upper];
+        let n = Self::INTERSECTION_SAMPLES_PER_SPAN;
+        let mut params = knots
+            .windows(2)
+            .flat_map(|w| {
+                let (a, b) = (w[0].value, w[1].value);
+                (0..n).map(move |i| a + (b - a) * i as Real / n as Real)
+            })
+            .collect::<Vec<_>>();
+        params.push(self.upper_parameter());
+        params
+    }
+
+    /// Distance between two parameters, measured around the seam when periodic.
+    fn parameter_gap(&self, s: Real, t: Real) -> Real {
+        let d = (s - t).abs();
+        if self.is_periodic {
+            d.min(self.upper_parameter() - self.lower_parameter() - d)
+        } else {
+            d
+        }
+    }
+
+    fn fit_parameter(&self, u: Real) -> Real {
+        if self.is_periodic {
+            self.domain().wrap(u)
+        } else {
+            self.domain().clamp(u)
+        }
+    }
+
+    fn refine_intersection(&self, s: Real, t: Real, tolerance: Real) -> Option<(Real, Real)> {
+        let (s, t) = closest_on_curves(
+            |s| self.derivatives(s, 1),
+            |t| self.derivatives(t, 1),
+            |s, t| (self.fit_parameter(s), self.fit_parameter(t)),
+            (s, t),
+            tolerance * 1e-3,
+            Self::INTERSECTION_MAX_ITERATIONS,
+        );
+        if self.interop(s).distance(self.interop(t)) <= tolerance {
+            Some((s, t))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole};
+    use crate::precision::{consts, Vec3};
+
+    #[test]
+    fn closed_within_tolerance_has_no_seam_intersection() {
+        let flatten = [0., 0., 0., 0., 0.25, 0.5, 0.75, 1., 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 7, false).unwrap();
+        // Simple loop, its end a little off its start.
+        let poles = [
+            Vec3::ZERO,
+            Vec3::new(2., -1., 0.),
+            Vec3::new(3., 1., 0.),
+            Vec3::new(2., 3., 0.),
+            Vec3::new(0., 3., 0.),
+            Vec3::new(-1., 1., 0.),
+            Vec3::new(1e-5, 0., 0.),
+        ]
+        .map(|pole| BSplinePole { pole, weight: 1. })
+        .to_vec();
+        let curve = BSpline::try_new(3, knots, poles, false).unwrap();
+        assert!(curve.self_intersections(1e-4).is_empty());
+    }
+
+    #[test]
+    fn loop_intersection_meets_itself() {
+        let flatten = [0., 0., 0., 0., 0.5, 1., 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 5, false).unwrap();
+        let poles = [
+            Vec3::ZERO,
+            Vec3::new(4., 0., 0.),
+            Vec3::new(4., 2., 0.),
+            Vec3::new(2., 2., 0.),
+            Vec3::new(2., -2., 0.),
+        ]
+        .map(|pole| BSplinePole { pole, weight: 1. })
+        .to_vec();
+        let curve = BSpline::try_new(3, knots, poles, false).unwrap();
+        let found = curve.self_intersections(1e-4);
+        assert_eq!(found.len(), 1);
+        let (s, t) = found[0];
+        assert!(s < t);
+        assert!(curve.interop(s).distance(curve.interop(t)) < 1e-4);
+    }
+
+    #[test]
+    fn periodic_intersection_across_seam() {
+        let n = 8;
+        let flatten = (0..=n).map(|i| i as Real / n as Real).collect::<Vec<_>>();
+        // Figure eight crossing itself at `u = (3 - shift) / 8` and half a period later.
+        for shift in [2.9, 3., 3.1] {
+            let knots = BSplineKnots::try_from_flatten(&flatten, 3, n, true).unwrap();
+            let poles = (0..n)
+                .map(|i| {
+                    let a = (i as Real + shift) * consts::TAU / n as Real;
+                    BSplinePole {
+                        pole: Vec3::new(a.sin(), a.sin() * a.cos(), 0.),
+                        weight: 1.,
+                    }
+                })
+                .collect();
+            let curve = BSpline::try_new(3, knots, poles, false).unwrap();
+            let found = curve.self_intersections(1e-4);
+            assert_eq!(found.len(), 1);
+            let (s, t) = found[0];
+            assert!(curve.interop(s).distance(curve.interop(t)) < 1e-4);
+            let crossing = (3. - shift) / 8.;
+            assert!((curve.parameter_gap(s, t) - 0.5).abs() < 1e-3);
+            assert!(
+                curve.parameter_gap(s, crossing) < 1e-3 || curve.parameter_gap(t, crossing) < 1e-3
+            );
+        }
+    }
+}
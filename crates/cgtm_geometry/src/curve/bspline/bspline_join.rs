@@ -0,0 +1,152 @@
+use crate::precision::Real;
+
+use super::{bspline_error::BSplineJoinError, BSpline};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BSplineJoin {
+    /// Maximum gap between consecutive end points, and maximum deviation allowed
+    /// when removing junction knots.
+    pub tolerance: Real,
+    /// Requested `C^k` continuity at the junctions, `0` keeps them as corners.
+    pub continuity: usize,
+}
+
+impl BSplineJoin {
+    /// Merges a chain of curves into a single clamped `BSpline`.
+    ///
+    /// Curves are reversed when needed to follow the chain, elevated to the highest
+    /// degree, and each domain is shifted to start where the previous one ends.
+    /// Junctions get a `degree`-multiplicity knot, which is then removed up to
+    /// `continuity` times while the curve moves less than `tolerance`.
+    /// If any curve is rational, the result is rational.
+    pub fn try_join(&self, curves: &[BSpline]) -> Result<BSpline, BSplineJoinError> {
+        if curves.is_empty() {
+            return Err(BSplineJoinError::NoCurves);
+        }
+        let mut curves = curves.to_vec();
+        curves.iter_mut().for_each(|curve| curve.clamp());
+        self.orient(&mut curves)?;
+
+        let degree = curves.iter().map(|c| c.degree).max().unwrap();
+        let use_rational = curves.iter().any(|c| c.use_rational);
+        curves.iter_mut().for_each(|curve| {
+            curve.elevate_degree(degree - curve.degree);
+            if use_rational && !curve.use_rational {
+                curve.poles.iter_mut().for_each(|p| p.weight = 1.);
+            }
+            curve.use_rational = use_rational;
+        });
+
+        let mut flatten = curves[0].knots.flatten.clone();
+        let mut pw = curves[0].homogeneous_poles();
+        let mut junctions = vec![];
+        for curve in curves.iter().skip(1) {
+            let end = flatten.pop().unwrap();
+            let shift = end - curve.lower_parameter();
+            flatten.extend(curve.knots.flatten[degree + 1..].iter().map(|v| v + shift));
+            junctions.push(end);
+
+            let mut qw = curve.homogeneous_poles();
+            // Scaling the weights keeps the curve, match them at the junction.
+            let factor = pw[pw.len() - 1].w / qw[0].w;
+            qw.iter_mut().for_each(|q| *q *= factor);
+            let last = pw.len() - 1;
+            pw[last] = 0.5 * (pw[last] + qw[0]);
+            pw.extend_from_slice(&qw[1..]);
+        }
+
+        let mut joined = curves.swap_remove(0);
+        joined.set_flatten_poles(&flatten, &pw)?;
+        junctions.into_iter().for_each(|u| {
+            joined.remove_knot(u, self.continuity.min(degree), self.tolerance);
+        });
+        Ok(joined)
+    }
+
+    fn orient(&self, curves: &mut [BSpline]) -> Result<(), BSplineJoinError> {
+        let start = |c: &BSpline| c.interop(c.lower_parameter());
+        let end = |c: &BSpline| c.interop(c.upper_parameter());
+        for i in 1..curves.len() {
+            let previous_end = end(&curves[i - 1]);
+            if previous_end.distance(start(&curves[i])) <= self.tolerance {
+                continue;
+            }
+            if previous_end.distance(end(&curves[i])) <= self.tolerance {
+                curves[i].reverse();
+                continue;
+            }
+            // The first curve is free to be reversed too.
+            if i == 1 {
+                let previous_start = start(&curves[0]);
+                if previous_start.distance(start(&curves[1])) <= self.tolerance {
+                    curves[0].reverse();
+                    continue;
+                }
+                if previous_start.distance(end(&curves[1])) <= self.tolerance {
+                    curves[0].reverse();
+                    curves[1].reverse();
+                    continue;
+                }
+            }
+            return Err(BSplineJoinError::NotConnected(i));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole};
+    use crate::precision::Vec3;
+
+    fn curve(flatten: &[Real], degree: usize, poles: &[Vec3]) -> BSpline {
+        let knots = BSplineKnots::try_from_flatten(flatten, degree, poles.len(), false).unwrap();
+        let poles = poles
+            .iter()
+            .map(|&pole| BSplinePole { pole, weight: 1. })
+            .collect();
+        BSpline::try_new(degree, knots, poles, false).unwrap()
+    }
+
+    #[test]
+    fn join_keeps_shape() {
+        let first = curve(
+            &[0., 0., 0., 1., 1., 1.],
+            2,
+            &[Vec3::ZERO, Vec3::new(1., 1., 0.), Vec3::new(2., 0., 0.)],
+        );
+        // Given backwards, on `[2, 3]`, ending where `first` ends.
+        let second = curve(
+            &[2., 2., 2., 2., 2.5, 3., 3., 3., 3.],
+            3,
+            &[
+                Vec3::new(4., 1., 1.),
+                Vec3::new(3.5, 0., 1.),
+                Vec3::new(3., -1., 0.),
+                Vec3::new(2.5, -0.5, 0.),
+                Vec3::new(2., 0., 0.),
+            ],
+        );
+        let join = BSplineJoin {
+            tolerance: 1e-5,
+            continuity: 0,
+        };
+        let joined = join.try_join(&[first.clone(), second.clone()]).unwrap();
+        assert_eq!(joined.degree, 3);
+        assert_eq!(joined.lower_parameter(), 0.);
+        assert_eq!(joined.upper_parameter(), 2.);
+        for i in 0..=20 {
+            let t = i as Real / 20.;
+            assert!(joined.interop(t).distance(first.interop(t)) < 1e-5);
+            // `second` reversed follows `first`, its domain shifted to `[1, 2]`.
+            assert!(joined.interop(1. + t).distance(second.interop(3. - t)) < 1e-5);
+        }
+
+        let gap = curve(&[0., 0., 1., 1.], 1, &[Vec3::X * 5., Vec3::X * 6.]);
+        assert!(matches!(
+            join.try_join(&[first, gap]),
+            Err(BSplineJoinError::NotConnected(1))
+        ));
+    }
+}
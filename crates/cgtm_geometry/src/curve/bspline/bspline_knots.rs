@@ -1,10 +1,12 @@
+use crate::precision::Real;
+
 use super::bspline_error::{BSplineError, BSplineKnotsGenError};
 
 #[derive(Debug, Clone)]
 pub enum BSplineKnotsAlgo {
-    DeBoor(Vec<f32>), // parameter
-    Uniform(usize),   // nb_poles
-    Universal(usize), // nb_poles
+    DeBoor(Vec<Real>), // parameter
+    Uniform(usize),    // nb_poles
+    Universal(usize),  // nb_poles
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -18,17 +20,18 @@ pub enum BSplineFeature {
 
 #[derive(Debug, Clone, Copy)]
 pub struct BSplineKnot {
-    pub value: f32,
+    pub value: Real,
     pub multiplicity: usize,
 }
 
+/// Knots of a `BSpline`, built for a degree, a number of poles and a periodicity.
 #[derive(Debug, Clone)]
 pub struct BSplineKnots {
-    pub knots: Vec<BSplineKnot>,
-    pub lower: usize,
-    pub upper: usize,
-    pub flatten: Vec<f32>,
-    pub n_extend: usize,
+    pub(crate) knots: Vec<BSplineKnot>,
+    pub(crate) lower: usize,
+    pub(crate) upper: usize,
+    pub(crate) flatten: Vec<Real>,
+    pub(crate) n_extend: usize,
 }
 
 impl BSplineKnots {
@@ -40,40 +43,68 @@ impl BSplineKnots {
     ) -> Result<(), BSplineError> {
         let m = knots.len();
         if m < 2 {
-            return Err(BSplineError::TooFewKnots);
+            return Err(BSplineError::TooFewKnots(m));
         }
-        if nb_poles < 2 {
-            return Err(BSplineError::TooFewPoles);
+        // The domain `[knots[degree], knots[nb_poles]]` needs `degree + 1` poles.
+        let min = (degree + 1).max(2);
+        if nb_poles < min {
+            return Err(BSplineError::TooFewPoles {
+                degree,
+                nb_poles,
+                min,
+            });
         }
-        for i in 0..m {
-            // check multiplicity
-            let max_multiplicity = if (i == 0 || i == m - 1) && !is_periodic {
+        for (index, knot) in knots.iter().enumerate() {
+            if !knot.value.is_finite() {
+                return Err(BSplineError::NonFiniteKnot {
+                    index,
+                    value: knot.value,
+                });
+            }
+            let max = if (index == 0 || index == m - 1) && !is_periodic {
                 degree + 1
             } else {
                 degree
             };
-            if knots[i].multiplicity > max_multiplicity {
-                return Err(BSplineError::MultiplicityOverDegree);
+            if knot.multiplicity > max {
+                return Err(BSplineError::MultiplicityOverDegree {
+                    index,
+                    multiplicity: knot.multiplicity,
+                    max,
+                    degree,
+                });
             }
             // Knots should are increasing.
-            if i > 0 && knots[i].value <= knots[i - 1].value {
-                return Err(BSplineError::MultiplicityOverDegree);
+            if index > 0 && knot.value <= knots[index - 1].value {
+                return Err(BSplineError::NonIncreasingKnots {
+                    index,
+                    value: knot.value,
+                    previous: knots[index - 1].value,
+                });
             }
         }
-        if is_periodic {
+        let total = knots.iter().map(|k| k.multiplicity).sum::<usize>();
+        let expected = if is_periodic {
             // First need to be the same with last.
             // First and last as same, then nb_knots should equal nb_poles.
             if knots[0].multiplicity != knots[m - 1].multiplicity {
-                return Err(BSplineError::PeriodicEdgeNotMatch);
-            }
-            if nb_poles != knots.iter().skip(1).map(|k| k.multiplicity).sum::<usize>() {
-                return Err(BSplineError::KnotsNumberNotMatch);
+                return Err(BSplineError::PeriodicEdgeNotMatch {
+                    first: knots[0].multiplicity,
+                    last: knots[m - 1].multiplicity,
+                });
             }
+            nb_poles + knots[0].multiplicity
         } else {
             // nb_knots = nb_poles + degree + 1.
-            if nb_poles + degree + 1 != knots.iter().map(|k| k.multiplicity).sum::<usize>() {
-                return Err(BSplineError::KnotsNumberNotMatch);
-            }
+            nb_poles + degree + 1
+        };
+        if total != expected {
+            return Err(BSplineError::KnotsNumberNotMatch {
+                degree,
+                nb_poles,
+                expected,
+                actual: total,
+            });
         }
         Ok(())
     }
@@ -96,10 +127,10 @@ impl BSplineKnots {
         k_idx
     }
 
-    fn extend_knots(knots: &[BSplineKnot], degree: usize) -> Vec<f32> {
+    fn extend_knots(knots: &[BSplineKnot], degree: usize) -> Vec<Real> {
         let period = knots.last().unwrap().value - knots[0].value;
         let split = degree + 1 - knots[0].multiplicity;
-        let mut extend = vec![0f32; 2 * split];
+        let mut extend = vec![0.; 2 * split];
         let e_start = split - 1;
         let e_end = split;
         let mut i_start = knots.len() - 2;
@@ -154,7 +185,7 @@ impl BSplineKnots {
         let n_extend = if is_periodic {
             let extend = Self::extend_knots(&knots, degree);
             let split = extend.len() / 2;
-            let mut extended = vec![0f32; flatten.len() + extend.len()];
+            let mut extended = vec![0.; flatten.len() + extend.len()];
             extended[..split].clone_from_slice(&extend[..split]);
             extended[split..flatten.len() + split].clone_from_slice(&flatten);
             extended[flatten.len() + split..].clone_from_slice(&extend[split..]);
@@ -172,6 +203,26 @@ impl BSplineKnots {
         })
     }
 
+    /// Builds knots from a flat knot vector, equal values are merged into multiplicities.
+    pub fn try_from_flatten(
+        flatten: &[Real],
+        degree: usize,
+        nb_poles: usize,
+        is_periodic: bool,
+    ) -> Result<Self, BSplineError> {
+        let mut knots: Vec<BSplineKnot> = vec![];
+        for &value in flatten {
+            match knots.last_mut() {
+                Some(knot) if knot.value == value => knot.multiplicity += 1,
+                _ => knots.push(BSplineKnot {
+                    value,
+                    multiplicity: 1,
+                }),
+            }
+        }
+        Self::try_new(knots, degree, nb_poles, is_periodic)
+    }
+
     pub fn try_build(
         degree: usize,
         algo: &BSplineKnotsAlgo,
@@ -186,7 +237,50 @@ impl BSplineKnots {
 }
 
 impl BSplineKnots {
-    pub fn original_flatten(&self) -> Vec<f32> {
+    pub fn knots(&self) -> &[BSplineKnot] {
+        &self.knots
+    }
+
+    /// Index of the knot starting the domain.
+    pub fn lower(&self) -> usize {
+        self.lower
+    }
+
+    /// Index of the knot ending the domain.
+    pub fn upper(&self) -> usize {
+        self.upper
+    }
+
+    /// Flat knot vector, extended by `n_extend` knots on each side when periodic.
+    pub fn flatten(&self) -> &[Real] {
+        &self.flatten
+    }
+
+    pub fn n_extend(&self) -> usize {
+        self.n_extend
+    }
+
+    /// Periodic knots are extended around the seam.
+    pub fn is_periodic(&self) -> bool {
+        self.n_extend > 0
+    }
+
+    /// Checks the knots were built for `degree` and `nb_poles`.
+    pub fn check_for(&self, degree: usize, nb_poles: usize) -> Result<(), BSplineError> {
+        Self::check(&self.knots, degree, nb_poles, self.is_periodic())?;
+        let built_for_degree = if self.is_periodic() {
+            self.n_extend == degree + 1 - self.knots[0].multiplicity
+        } else {
+            self.lower == Self::non_periodic_bound(&self.knots, degree, true)
+                && self.upper == Self::non_periodic_bound(&self.knots, degree, false)
+        };
+        if !built_for_degree {
+            return Err(BSplineError::DegreeNotMatch { degree });
+        }
+        Ok(())
+    }
+
+    pub fn original_flatten(&self) -> Vec<Real> {
         self.knots
             .iter()
             .flat_map(|knot| vec![knot.value; knot.multiplicity])
@@ -198,7 +292,18 @@ impl BSplineKnots {
         knot_index: usize,
         degree: usize,
         is_periodic: bool,
-    ) -> Vec<f32> {
+    ) -> Vec<Real> {
+        self.get_knots_bounds_slice(knot_index, degree, is_periodic)
+            .to_vec()
+    }
+
+    /// Same as [`BSplineKnots::get_knots_bounds`], borrowed from `flatten`.
+    pub fn get_knots_bounds_slice(
+        &self,
+        knot_index: usize,
+        degree: usize,
+        is_periodic: bool,
+    ) -> &[Real] {
         let idx = self
             .knots
             .iter()
@@ -211,12 +316,32 @@ impl BSplineKnots {
             idx.max(degree).min(self.flatten.len() - degree - 1)
         };
         let idx = idx + self.n_extend - 1;
-        (idx - degree + 1..=idx + degree)
-            .map(|i| self.flatten[i])
-            .collect()
+        &self.flatten[idx + 1 - degree..=idx + degree]
+    }
+
+    /// Index of the first pole of the span starting at knot `knot_index`.
+    pub fn get_pole_index(&self, knot_index: usize, degree: usize, is_periodic: bool) -> usize {
+        let pole_index = self
+            .knots
+            .iter()
+            .take(knot_index + 1)
+            .map(|knot| knot.multiplicity)
+            .sum::<usize>();
+        if is_periodic {
+            pole_index - self.knots[self.lower].multiplicity
+        } else {
+            pole_index - (degree + 1)
+        }
+    }
+
+    /// Same as [`BSplineKnots::get_knot_index`], parameters past the domain use the
+    /// first or last span.
+    pub fn get_span_index(&self, u: Real) -> usize {
+        self.get_knot_index(u).min(self.upper - 1)
     }
 
-    pub fn get_knot_index(&self, u: f32) -> usize {
+    /// Index of the knot starting the span of `u`, clamped to `lower..=upper`.
+    pub fn get_knot_index(&self, u: Real) -> usize {
         let mut idx = 0;
         for i in 1..self.knots.len() {
             if self.knots[i].value < u {
@@ -226,7 +351,7 @@ impl BSplineKnots {
         idx.max(self.lower).min(self.upper)
     }
 
-    pub fn get_flat_index(&self, u: f32) -> usize {
+    pub fn get_flat_index(&self, u: Real) -> usize {
         let u_id = self.get_knot_index(u);
         let idx = self
             .knots
@@ -238,10 +363,40 @@ impl BSplineKnots {
         idx + self.n_extend
     }
 
-    pub fn lower_value(&self) -> f32 {
+    /// Mirrors the knots so that `u` becomes `lower + upper - u`, the domain is unchanged.
+    pub fn reverse(&mut self) {
+        let (lower, upper) = (self.lower_value(), self.upper_value());
+        let mirror = |v: Real| lower + upper - v;
+        self.knots.reverse();
+        self.knots
+            .iter_mut()
+            .for_each(|k| k.value = mirror(k.value));
+        self.flatten.reverse();
+        self.flatten.iter_mut().for_each(|v| *v = mirror(*v));
+        let m = self.knots.len() - 1;
+        (self.lower, self.upper) = (m - self.upper, m - self.lower);
+    }
+
+    /// Affine remap of every knot so that the domain becomes `[new_lower, new_upper]`.
+    pub fn reparameterize(&mut self, new_lower: Real, new_upper: Real) -> Result<(), BSplineError> {
+        if !new_lower.is_finite() || !new_upper.is_finite() || new_lower >= new_upper {
+            return Err(BSplineError::EmptyDomain {
+                lower: new_lower,
+                upper: new_upper,
+            });
+        }
+        let (lower, upper) = (self.lower_value(), self.upper_value());
+        let factor = (new_upper - new_lower) / (upper - lower);
+        let remap = |v: Real| new_lower + (v - lower) * factor;
+        self.knots.iter_mut().for_each(|k| k.value = remap(k.value));
+        self.flatten.iter_mut().for_each(|v| *v = remap(*v));
+        Ok(())
+    }
+
+    pub fn lower_value(&self) -> Real {
         self.knots[self.lower].value
     }
-    pub fn upper_value(&self) -> f32 {
+    pub fn upper_value(&self) -> Real {
         self.knots[self.upper].value
     }
 }
@@ -268,7 +423,7 @@ fn new_uniform(
         return Err(BSplineKnotsGenError::TooFewPoles);
     }
     let mid = total - first - last;
-    let d = 1. / (mid + 1) as f32;
+    let d = 1. / (mid + 1) as Real;
 
     let mut knots = vec![BSplineKnot {
         value: 0.,
@@ -276,7 +431,7 @@ fn new_uniform(
     }];
     for i in 0..mid {
         knots.push(BSplineKnot {
-            value: (i + 1) as f32 * d,
+            value: (i + 1) as Real * d,
             multiplicity: 1,
         });
     }
@@ -313,14 +468,14 @@ fn new_universal(
         return Err(BSplineKnotsGenError::TooFewPoles);
     }
     let mid = total - first - last;
-    let factor = (nb_poles + 1 - degree) as f32;
+    let factor = (nb_poles + 1 - degree) as Real;
     let mut knots = vec![BSplineKnot {
         value: 0.,
         multiplicity: first,
     }];
     for i in 0..mid {
         knots.push(BSplineKnot {
-            value: (i + 1) as f32 / factor,
+            value: (i + 1) as Real / factor,
             multiplicity: 1,
         });
     }
@@ -337,7 +492,7 @@ fn new_universal(
 
 pub fn new_de_boor(
     _degree: usize,
-    _de_boor_param: &[f32],
+    _de_boor_param: &[Real],
     _feature: BSplineFeature,
 ) -> Result<BSplineKnots, BSplineKnotsGenError> {
     todo!()
@@ -347,7 +502,7 @@ pub fn new_de_boor(
 // // nb_knots = (n + p + 1) = nb_parameter + degree
 // pub fn new_de_boor_old(
 //     degree: usize,
-//     de_boor_param: Vec<f32>
+//     de_boor_param: Vec<Real>
 // ) -> Option<Self> {
 //     if de_boor_param.is_empty() {
 //         return None;
@@ -365,17 +520,89 @@ pub fn new_de_boor(
 //     let u0 = de_boor_param[0];
 //     let u1 = *de_boor_param.last().unwrap();
 //     let d = u1 - u0;
-//     let de_boor_param = de_boor_param.into_iter().map(|v| v / d).collect::<Vec<f32>>();
+//     let de_boor_param = de_boor_param.into_iter().map(|v| v / d).collect::<Vec<Real>>();
 
 //     let len = degree + de_boor_param.len();
-//     let mut knots = vec![0f32; len];
+//     let mut knots = vec![0.; len];
 //     let start = degree + 1;
 //     let end = len - degree - 1;
 //     for knot in end..len {
 //         knots[knot] = d;
 //     }
 //     for k in start..end {
-//         knots[k] = (k-degree..k-1).map(|i| de_boor_param[i]).sum::<f32>() / degree as f32 * d;
+//         knots[k] = (k-degree..k-1).map(|i| de_boor_param[i]).sum::<Real>() / degree as Real * d;
 //     }
 //     Some(Self { knots, start: u0, end: u1 })
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn knots(values: &[(Real, usize)]) -> Vec<BSplineKnot> {
+        values
+            .iter()
+            .map(|&(value, multiplicity)| BSplineKnot {
+                value,
+                multiplicity,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn check_accepts_valid_knots() {
+        let clamped = knots(&[(0., 4), (0.5, 1), (1., 4)]);
+        assert!(BSplineKnots::check(&clamped, 3, 5, false).is_ok());
+        let periodic = knots(&[(0., 1), (0.25, 1), (0.5, 1), (0.75, 1), (1., 1)]);
+        assert!(BSplineKnots::check(&periodic, 3, 4, true).is_ok());
+    }
+
+    #[test]
+    fn check_errors() {
+        let check = |values: &[(Real, usize)], degree, nb_poles, periodic| {
+            BSplineKnots::check(&knots(values), degree, nb_poles, periodic).unwrap_err()
+        };
+        assert!(matches!(
+            check(&[(0., 4)], 3, 4, false),
+            BSplineError::TooFewKnots(1)
+        ));
+        // Used to pass with `lower > upper`.
+        assert!(matches!(
+            check(&[(0., 3), (1., 3)], 3, 2, false),
+            BSplineError::TooFewPoles {
+                degree: 3,
+                nb_poles: 2,
+                min: 4
+            }
+        ));
+        assert!(matches!(
+            check(&[(0., 4), (Real::NAN, 1), (1., 4)], 3, 5, false),
+            BSplineError::NonFiniteKnot { index: 1, .. }
+        ));
+        assert!(matches!(
+            check(&[(0., 4), (0.5, 4), (1., 4)], 3, 8, false),
+            BSplineError::MultiplicityOverDegree {
+                index: 1,
+                multiplicity: 4,
+                max: 3,
+                ..
+            }
+        ));
+        assert!(matches!(
+            check(&[(0., 4), (1., 1), (0.5, 4)], 3, 5, false),
+            BSplineError::NonIncreasingKnots { index: 2, .. }
+        ));
+        assert!(matches!(
+            check(&[(0., 1), (0.5, 1), (1., 2)], 2, 3, true),
+            BSplineError::PeriodicEdgeNotMatch { first: 1, last: 2 }
+        ));
+        assert!(matches!(
+            check(&[(0., 4), (0.5, 1), (1., 4)], 3, 6, false),
+            BSplineError::KnotsNumberNotMatch {
+                expected: 10,
+                actual: 9,
+                ..
+            }
+        ));
+    }
+}
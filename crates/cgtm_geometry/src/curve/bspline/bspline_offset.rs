@@ -0,0 +1,271 @@
+use crate::precision::{Real, Vec3};
+
+use super::{
+    bspline_approximation::BSplineApproximation,
+    bspline_data_knots::BSplineDataKnotsAlgo,
+    bspline_error::BSplineOffsetError,
+    bspline_join::BSplineJoin,
+    bspline_knots::{BSplineFeature, BSplineKnotsAlgo},
+    utils::closest_on_segments,
+    BSpline,
+};
+
+impl BSpline {
+    /// Number of offset points sampled inside each knot span.
+    pub const OFFSET_SAMPLES_PER_SPAN: usize = 64;
+
+    /// Offset of a planar curve by a signed `distance` in the plane of `normal`.
+    ///
+    /// Positive distances move to the left of the tangent, i.e. along `normal x C'(u)`.
+    /// The exact offset is sampled, local loops (where `distance` exceeds the radius of
+    /// curvature) are trimmed, and the result is approximated by a clamped `BSpline`
+    /// of the same degree whose poles are increased until the samples are within
+    /// `tolerance`. Trimmed corners are kept as `degree`-multiplicity knots.
+    /// Periodic curves give a closed, clamped curve.
+    pub fn try_offset(
+        &self,
+        distance: Real,
+        normal: Vec3,
+        tolerance: Real,
+    ) -> Result<BSpline, BSplineOffsetError> {
+        let normal = normal
+            .try_normalize()
+            .ok_or(BSplineOffsetError::NotPlanar)?;
+        let origin = self.poles[0].pole;
+        if self
+            .poles
+            .iter()
+            .any(|p| (p.pole - origin).dot(normal).abs() > tolerance)
+        {
+            return Err(BSplineOffsetError::NotPlanar);
+        }
+
+        let (lower, upper) = (self.lower_parameter(), self.upper_parameter());
+        let nb_samples = (self.knots.upper - self.knots.lower) * Self::OFFSET_SAMPLES_PER_SPAN;
+        let samples = (0..=nb_samples)
+            .map(|i| {
+                let u = lower + (upper - lower) * i as Real / nb_samples as Real;
+                let ders = self.derivatives(u, 1);
+                let side = normal
+                    .cross(ders[1])
+                    .try_normalize()
+                    .ok_or(BSplineOffsetError::DegenerateTangent)?;
+                Ok(OffsetSample {
+                    point: ders[0] + distance * side,
+                    u_end: u,
+                    u_start: u,
+                })
+            })
+            .collect::<Result<Vec<_>, BSplineOffsetError>>()?;
+
+        let samples = trim_local_loops(samples, self.is_periodic, tolerance);
+        if samples.len() < 2 {
+            return Err(BSplineOffsetError::EmptyOffset);
+        }
+        // Trimmed loops leave sharp corners, fit each smooth piece on its own.
+        let mut pieces = vec![];
+        let mut first = 0;
+        for i in 1..samples.len() {
+            if samples[i].is_corner() || i == samples.len() - 1 {
+                pieces.push(fit_offset(&samples[first..=i], self.degree, tolerance)?);
+                first = i;
+            }
+        }
+        let join = BSplineJoin {
+            tolerance,
+            continuity: 0,
+        };
+        Ok(join.try_join(&pieces)?)
+    }
+}
+
+/// Offset point with the curve parameter it comes from.
+///
+/// Crossing points of trimmed loops end a piece at `u_end` and start the next at `u_start`.
+#[derive(Debug, Clone, Copy)]
+struct OffsetSample {
+    point: Vec3,
+    u_end: Real,
+    u_start: Real,
+}
+
+impl OffsetSample {
+    fn is_corner(&self) -> bool {
+        self.u_end != self.u_start
+    }
+}
+
+/// Removes the loops of a polyline, replacing each loop by its crossing point.
+///
+/// When `closed`, a loop may also straddle the first/last point.
+fn trim_local_loops(
+    mut pts: Vec<OffsetSample>,
+    closed: bool,
+    tolerance: Real,
+) -> Vec<OffsetSample> {
+    let eps = tolerance * 1e-2;
+    let mut i = 0;
+    'outer: while i + 2 < pts.len() {
+        let nb_segments = pts.len() - 1;
+        for j in (i + 2..nb_segments).rev() {
+            if closed && i == 0 && j == nb_segments - 1 {
+                continue;
+            }
+            let (s, t, dist) = closest_on_segments(
+                pts[i].point,
+                pts[i + 1].point,
+                pts[j].point,
+                pts[j + 1].point,
+            );
+            if dist > eps {
+                continue;
+            }
+            let point = pts[i].point + s * (pts[i + 1].point - pts[i].point);
+            let u_i = pts[i].u_start + s * (pts[i + 1].u_end - pts[i].u_start);
+            let u_j = pts[j].u_start + t * (pts[j + 1].u_end - pts[j].u_start);
+            if closed && 2 * (j - i) > nb_segments {
+                // The loop is the part across the seam, keep the inner part.
+                let mut inner = Vec::with_capacity(j - i + 2);
+                inner.push(OffsetSample {
+                    point,
+                    u_end: u_i,
+                    u_start: u_i,
+                });
+                inner.extend_from_slice(&pts[i + 1..=j]);
+                inner.push(OffsetSample {
+                    point,
+                    u_end: u_j,
+                    u_start: u_j,
+                });
+                pts = inner;
+                i = 0;
+            } else {
+                let corner = OffsetSample {
+                    point,
+                    u_end: u_i,
+                    u_start: u_j,
+                };
+                pts.splice(i + 1..=j, [corner]);
+            }
+            continue 'outer;
+        }
+        i += 1;
+    }
+    pts
+}
+
+/// Least squares fit of a piece, parameterized by the curve parameter it comes from.
+fn fit_offset(
+    samples: &[OffsetSample],
+    degree: usize,
+    tolerance: Real,
+) -> Result<BSpline, BSplineOffsetError> {
+    let u_first = samples[0].u_start;
+    let u_last = samples[samples.len() - 1].u_end;
+    let mut pts = samples.iter().map(|s| s.point).collect::<Vec<_>>();
+    let mut params = samples.iter().map(|s| s.u_end).collect::<Vec<_>>();
+    params[0] = u_first;
+    // Short pieces between two corners are densified linearly.
+    while pts.len() < 2 * (degree + 1).max(3) {
+        pts = pts
+            .windows(2)
+            .flat_map(|w| [w[0], 0.5 * (w[0] + w[1])])
+            .chain([pts[pts.len() - 1]])
+            .collect();
+        params = params
+            .windows(2)
+            .flat_map(|w| [w[0], 0.5 * (w[0] + w[1])])
+            .chain([u_last])
+            .collect();
+    }
+    let params = params
+        .iter()
+        .map(|u| (u - u_first) / (u_last - u_first))
+        .collect::<Vec<_>>();
+
+    let max_poles = pts.len() / 2;
+    let mut nb_poles = (degree + 1).max(3);
+    while nb_poles <= max_poles {
+        let approx = BSplineApproximation {
+            degree,
+            nb_poles,
+            feature: BSplineFeature::ClampAll,
+            knots_algo: BSplineKnotsAlgo::Uniform(nb_poles),
+            data_knots_algo: BSplineDataKnotsAlgo::default(),
+        };
+        let bspline = approx.try_approximate_with_parameters(&pts, &params)?;
+        let within = params
+            .iter()
+            .zip(&pts)
+            .all(|(&u, &p)| bspline.interop(u).distance(p) <= tolerance);
+        if within {
+            return Ok(bspline);
+        }
+        nb_poles = if nb_poles == max_poles {
+            max_poles + 1
+        } else {
+            (2 * nb_poles).min(max_poles)
+        };
+    }
+    Err(BSplineOffsetError::ToleranceNotReached)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole};
+
+    #[test]
+    fn offset_of_arc_is_concentric() {
+        // Rational quarter circle of radius 1, counter-clockwise around the origin.
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 0., 1., 1., 1.], 2, 3, false);
+        let poles = [
+            (Vec3::X, 1.),
+            (Vec3::new(1., 1., 0.), Real::sqrt(0.5)),
+            (Vec3::Y, 1.),
+        ]
+        .map(|(pole, weight)| BSplinePole { pole, weight })
+        .to_vec();
+        let arc = BSpline::try_new(2, knots.unwrap(), poles, true).unwrap();
+        let tolerance = 1e-3;
+        for (distance, radius) in [(0.5, 0.5), (-0.5, 1.5)] {
+            let offset = arc.try_offset(distance, Vec3::Z, tolerance).unwrap();
+            let domain = offset.domain();
+            for i in 0..=100 {
+                let u = domain.lower + domain.length() * i as Real / 100.;
+                assert!((offset.interop(u).length() - radius).abs() < 2. * tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn offset_keeps_distance_near_cusp() {
+        // Sharp bend, the inner offset trims a loop where it exceeds the curvature radius.
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 0., 0., 1., 1., 1., 1.], 3, 4, false);
+        let poles = [
+            Vec3::ZERO,
+            Vec3::new(1., 2., 0.),
+            Vec3::new(1.1, 2., 0.),
+            Vec3::new(2.1, 0., 0.),
+        ]
+        .map(|pole| BSplinePole { pole, weight: 1. })
+        .to_vec();
+        let curve = BSpline::try_new(3, knots.unwrap(), poles, false).unwrap();
+        let polyline = (0..=2000)
+            .map(|i| curve.interop(i as Real / 2000.))
+            .collect::<Vec<_>>();
+        let tolerance = 1e-3;
+        for distance in [0.3, -0.3] {
+            let offset = curve.try_offset(distance, Vec3::Z, tolerance).unwrap();
+            let domain = offset.domain();
+            for i in 0..=200 {
+                let point = offset.interop(domain.lower + domain.length() * i as Real / 200.);
+                let gap = polyline
+                    .windows(2)
+                    .map(|w| closest_on_segments(point, point, w[0], w[1]).2)
+                    .fold(Real::MAX, Real::min);
+                assert!((gap - distance.abs()).abs() < 5. * tolerance);
+            }
+        }
+    }
+}
@@ -1,7 +1,78 @@
-use f3l::glam::Vec3;
+use std::fmt::Debug;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use crate::precision::{Real, Vec2, Vec3, Vec4};
+
+/// Value a `BSpline` interpolates: scalars, 2D, 3D or 4D points.
+pub trait ControlPoint:
+    Copy
+    + Send
+    + Sync
+    + Debug
+    + Default
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Neg<Output = Self>
+    + Mul<Real, Output = Self>
+    + Div<Real, Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+{
+    const DIM: usize;
+    const ZERO: Self;
+
+    fn coord(&self, i: usize) -> Real;
+    fn from_coords(coords: &[Real]) -> Self;
+    fn length(self) -> Real;
+
+    fn distance(self, other: Self) -> Real {
+        (self - other).length()
+    }
+}
+
+impl ControlPoint for Real {
+    const DIM: usize = 1;
+    const ZERO: Self = 0.;
+
+    fn coord(&self, _: usize) -> Real {
+        *self
+    }
+    fn from_coords(coords: &[Real]) -> Self {
+        coords[0]
+    }
+    fn length(self) -> Real {
+        self.abs()
+    }
+}
+
+macro_rules! impl_control_point {
+    ($t:ty, $dim:literal) => {
+        impl ControlPoint for $t {
+            const DIM: usize = $dim;
+            const ZERO: Self = <$t>::ZERO;
+
+            fn coord(&self, i: usize) -> Real {
+                self[i]
+            }
+            fn from_coords(coords: &[Real]) -> Self {
+                <$t>::from_slice(coords)
+            }
+            fn length(self) -> Real {
+                <$t>::length(self)
+            }
+        }
+    };
+}
+
+impl_control_point!(Vec2, 2);
+impl_control_point!(Vec3, 3);
+impl_control_point!(Vec4, 4);
 
 #[derive(Debug, Clone, Copy, Default)]
-pub struct BSplinePole {
-    pub pole: Vec3,
-    pub weight: f32,
+pub struct BSplinePole<P = Vec3> {
+    pub pole: P,
+    pub weight: Real,
 }
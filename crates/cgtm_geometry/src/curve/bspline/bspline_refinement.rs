@@ -0,0 +1,544 @@
+use crate::precision::{Real, Vec4};
+
+use super::{
+    bspline_error::BSplineError, bspline_knots::BSplineKnots, bspline_poles::BSplinePole, BSpline,
+};
+
+/// Knots of compatible curves closer than this, on `[0, 1]`, are merged.
+const KNOT_TOLERANCE: Real = 16. * Real::EPSILON;
+
+impl BSpline {
+    /// Gives curves the same degree, knots and rationality, their shapes unchanged.
+    ///
+    /// Curves are clamped, elevated to the highest degree and mapped to `[0, 1]`, then
+    /// each gets the knots of the others inserted. Knots of different curves within
+    /// rounding of each other are snapped together first, a curve's own distinct knots stay distinct.
+    /// On error, `curves` are left unchanged.
+    pub fn make_compatible(curves: &mut [BSpline]) -> Result<(), BSplineError> {
+        let mut compatible = curves.to_vec();
+        let degree = compatible.iter().map(|c| c.degree).max().unwrap_or(0);
+        let use_rational = compatible.iter().any(|c| c.use_rational);
+        for curve in compatible.iter_mut() {
+            curve.elevate_degree(degree - curve.degree);
+            if use_rational && !curve.use_rational {
+                curve.poles.iter_mut().for_each(|p| p.weight = 1.);
+            }
+            curve.use_rational = use_rational;
+            curve.reparameterize(0., 1.)?;
+        }
+
+        // Knots moved by rounding are snapped to the knots of the previous curves.
+        let mut values: Vec<Real> = vec![];
+        for curve in compatible.iter_mut() {
+            let mut claimed = vec![false; values.len()];
+            let own = curve.knots.knots.clone();
+            let snapped = own
+                .iter()
+                .map(|knot| {
+                    let nearest = values
+                        .iter()
+                        .enumerate()
+                        .filter(|&(i, &v)| !claimed[i] && (v - knot.value).abs() <= KNOT_TOLERANCE)
+                        .min_by(|a, b| {
+                            (a.1 - knot.value)
+                                .abs()
+                                .total_cmp(&(b.1 - knot.value).abs())
+                        });
+                    match nearest {
+                        Some((i, &v)) => {
+                            claimed[i] = true;
+                            v
+                        }
+                        None => knot.value,
+                    }
+                })
+                .collect::<Vec<_>>();
+            values.extend(
+                own.iter()
+                    .zip(&snapped)
+                    .filter(|(knot, &v)| knot.value == v && !values.contains(&v))
+                    .map(|(knot, _)| knot.value)
+                    .collect::<Vec<_>>(),
+            );
+            if own.iter().zip(&snapped).any(|(knot, &v)| knot.value != v) {
+                let flatten = own
+                    .iter()
+                    .zip(&snapped)
+                    .flat_map(|(knot, &v)| std::iter::repeat_n(v, knot.multiplicity))
+                    .collect::<Vec<_>>();
+                curve.set_flatten_poles(&flatten, &curve.homogeneous_poles())?;
+            }
+        }
+        values.sort_by(Real::total_cmp);
+
+        let multiplicity =
+            |c: &BSpline, value: Real| c.knots.flatten.iter().filter(|&&v| v == value).count();
+        for &value in &values {
+            let target = compatible
+                .iter()
+                .map(|c| multiplicity(c, value))
+                .max()
+                .unwrap();
+            compatible.iter_mut().for_each(|curve| {
+                let times = target - multiplicity(curve, value);
+                curve.insert_knot(value, times);
+            });
+        }
+        curves.clone_from_slice(&compatible);
+        Ok(())
+    }
+
+    /// Same curve as a non-periodic (unclamped) `BSpline`.
+    ///
+    /// The extended knots of the periodic curve become the knots, and the poles are
+    /// repeated around the seam.
+    pub fn to_non_periodic(&self) -> BSpline {
+        if !self.is_periodic {
+            return self.clone();
+        }
+        let flatten = self.knots.flatten.clone();
+        let nb_poles = flatten.len() - self.degree - 1;
+        let poles = (0..nb_poles)
+            .map(|i| self.poles[i % self.poles.len()])
+            .collect::<Vec<_>>();
+        let knots = BSplineKnots::try_from_flatten(&flatten, self.degree, nb_poles, false)
+            .expect("Unrolled periodic knots.");
+        BSpline {
+            degree: self.degree,
+            knots,
+            poles,
+            is_periodic: false,
+            use_rational: self.use_rational,
+        }
+    }
+
+    /// Clamps both ends, so the curve starts at the first pole and ends at the last.
+    ///
+    /// Periodic curves are converted with [`BSpline::to_non_periodic`] first.
+    pub fn clamp(&mut self) {
+        if self.is_periodic {
+            *self = self.to_non_periodic();
+        }
+        self.clamp_start();
+        self.reverse();
+        self.clamp_start();
+        self.reverse();
+    }
+
+    fn clamp_start(&mut self) {
+        let p = self.degree;
+        let a = self.lower_parameter();
+        let s = self.knots.flatten.iter().filter(|&&v| v == a).count();
+        if s < p {
+            self.insert_knot(a, p - s);
+        }
+        let (mut uk, mut pw) = (self.knots.flatten.clone(), self.homogeneous_poles());
+        // With `a` of multiplicity `p`, the curve starts at the pole before the first `a`.
+        let j = uk.iter().take_while(|&&v| v < a).count();
+        if j > 0 {
+            uk.drain(..j - 1);
+            uk[0] = a;
+            pw.drain(..j - 1);
+        }
+        self.set_flatten_poles(&uk, &pw)
+            .expect("Clamping keeps the knots valid.");
+    }
+
+    /// Inserts the knot `u` up to `times`, returns the number of insertions done.
+    ///
+    /// Multiplicity is capped to `degree`, and `u` should be in `[lower, upper)`.
+    /// Periodic curves are converted with [`BSpline::to_non_periodic`] first.
+    pub fn insert_knot(&mut self, u: Real, times: usize) -> usize {
+        if self.is_periodic {
+            *self = self.to_non_periodic();
+        }
+        if u < self.lower_parameter() || u >= self.upper_parameter() {
+            return 0;
+        }
+        let p = self.degree;
+        let uk = &self.knots.flatten;
+        let k = uk.iter().rposition(|&v| v <= u).unwrap();
+        let s = uk.iter().filter(|&&v| v == u).count();
+        let r = times.min(p.saturating_sub(s));
+        if r == 0 {
+            return 0;
+        }
+        let (uq, qw) = insert_knot(p, uk, &self.homogeneous_poles(), u, k, s, r);
+        self.set_flatten_poles(&uq, &qw)
+            .expect("Insertion keeps multiplicities within the degree.");
+        r
+    }
+
+    /// Removes the interior knot `u` up to `times` while the curve moves less than
+    /// `tolerance`, returns the number of removals done.
+    ///
+    /// Periodic curves are converted with [`BSpline::to_non_periodic`] first.
+    pub fn remove_knot(&mut self, u: Real, times: usize, tolerance: Real) -> usize {
+        if self.is_periodic {
+            *self = self.to_non_periodic();
+        }
+        let eps = Real::EPSILON * u.abs().max(1.);
+        let Some(idx) = self
+            .knots
+            .knots
+            .iter()
+            .position(|k| (k.value - u).abs() <= eps)
+        else {
+            return 0;
+        };
+        if idx <= self.knots.lower || idx >= self.knots.upper {
+            return 0;
+        }
+        let u = self.knots.knots[idx].value;
+        let s = self.knots.knots[idx].multiplicity;
+        let r = self.knots.flatten.iter().rposition(|&v| v == u).unwrap();
+
+        let (mut uk, mut pw) = (self.knots.flatten.clone(), self.homogeneous_poles());
+        // Tolerance in homogeneous space, see The NURBS Book (5.30).
+        let tolerance = if self.use_rational {
+            let w_min = pw.iter().map(|p| p.w).fold(Real::MAX, Real::min);
+            let p_max = self
+                .poles
+                .iter()
+                .map(|p| p.pole.length())
+                .fold(0., Real::max);
+            tolerance * w_min / (1. + p_max)
+        } else {
+            tolerance
+        };
+        let t = remove_knot(
+            self.degree,
+            &mut uk,
+            &mut pw,
+            u,
+            r,
+            s,
+            times.min(s),
+            tolerance,
+        );
+        if t > 0 {
+            self.set_flatten_poles(&uk, &pw)
+                .expect("Removal keeps the knots valid.");
+        }
+        t
+    }
+
+    /// Raises the degree by `times`, the curve is unchanged.
+    ///
+    /// The result is clamped, see [`BSpline::clamp`].
+    pub fn elevate_degree(&mut self, times: usize) {
+        self.clamp();
+        if times == 0 {
+            return;
+        }
+        let p = self.degree;
+        let ph = p + times;
+        let interior = self.knots.knots[1..self.knots.knots.len() - 1].to_vec();
+
+        // Decompose into Bezier segments.
+        interior.iter().for_each(|knot| {
+            self.insert_knot(knot.value, p - knot.multiplicity);
+        });
+        let pw = self.homogeneous_poles();
+
+        let mut qw = vec![pw[0]];
+        for segment in pw.windows(p + 1).step_by(p) {
+            let elevated = (0..=ph)
+                .map(|i| {
+                    (i.saturating_sub(times)..=p.min(i))
+                        .map(|j| {
+                            binomial(p, j) * binomial(times, i - j) / binomial(ph, i) * segment[j]
+                        })
+                        .sum::<Vec4>()
+                })
+                .collect::<Vec<_>>();
+            qw.extend_from_slice(&elevated[1..]);
+        }
+        let (a, b) = (self.lower_parameter(), self.upper_parameter());
+        let mut uq = vec![a; ph + 1];
+        interior
+            .iter()
+            .for_each(|knot| uq.extend(std::iter::repeat_n(knot.value, ph)));
+        uq.extend(std::iter::repeat_n(b, ph + 1));
+
+        self.degree = ph;
+        self.set_flatten_poles(&uq, &qw)
+            .expect("Elevated knots have multiplicity the new degree.");
+        // Restore the original continuity, exact so no tolerance needed.
+        interior.iter().for_each(|knot| {
+            self.remove_knot(knot.value, p - knot.multiplicity, Real::INFINITY);
+        });
+    }
+
+    /// Poles as `(w * P, w)`, `w = 1` when not rational.
+    pub(crate) fn homogeneous_poles(&self) -> Vec<Vec4> {
+        self.poles
+            .iter()
+            .map(|p| {
+                let w = if self.use_rational { p.weight } else { 1. };
+                (w * p.pole).extend(w)
+            })
+            .collect()
+    }
+
+    /// Replaces the poles from homogeneous ones and the knots from a flat knot vector,
+    /// the curve becomes non-periodic.
+    ///
+    /// Fails if `flatten` is not a knot vector for the degree and poles, the curve is
+    /// then unchanged.
+    pub(crate) fn set_flatten_poles(
+        &mut self,
+        flatten: &[Real],
+        pw: &[Vec4],
+    ) -> Result<(), BSplineError> {
+        self.knots = BSplineKnots::try_from_flatten(flatten, self.degree, pw.len(), false)?;
+        self.poles = pw
+            .iter()
+            .map(|p| BSplinePole {
+                pole: p.truncate() / p.w,
+                weight: p.w,
+            })
+            .collect();
+        self.is_periodic = false;
+        Ok(())
+    }
+}
+
+pub(crate) fn binomial(n: usize, k: usize) -> Real {
+    (0..k).fold(1., |acc, i| acc * (n - i) as Real / (i + 1) as Real)
+}
+
+/// Knot insertion, The NURBS Book A5.1.
+///
+/// Inserts `u` (in span `k`, multiplicity `s`) `r` times into knots `uk` and poles `pw`.
+fn insert_knot(
+    p: usize,
+    uk: &[Real],
+    pw: &[Vec4],
+    u: Real,
+    k: usize,
+    s: usize,
+    r: usize,
+) -> (Vec<Real>, Vec<Vec4>) {
+    let np = pw.len() - 1;
+    let mut uq = Vec::with_capacity(uk.len() + r);
+    uq.extend_from_slice(&uk[..=k]);
+    uq.extend(std::iter::repeat_n(u, r));
+    uq.extend_from_slice(&uk[k + 1..]);
+
+    let mut qw = vec![Vec4::ZERO; np + r + 1];
+    qw[..=k - p].copy_from_slice(&pw[..=k - p]);
+    qw[k - s + r..].copy_from_slice(&pw[k - s..]);
+    let mut rw = pw[k - p..=k - s].to_vec();
+    let mut l = 0;
+    for j in 1..=r {
+        l = k - p + j;
+        for i in 0..=p - j - s {
+            let alpha = (u - uk[l + i]) / (uk[i + k + 1] - uk[l + i]);
+            rw[i] = alpha * rw[i + 1] + (1. - alpha) * rw[i];
+        }
+        qw[l] = rw[0];
+        qw[k + r - j - s] = rw[p - j - s];
+    }
+    if l + 1 < k - s {
+        qw[l + 1..k - s].copy_from_slice(&rw[1..k - s - l]);
+    }
+    (uq, qw)
+}
+
+/// Knot removal, The NURBS Book A5.8.
+///
+/// Tries to remove `u` (last index `r`, multiplicity `s`) `num` times from knots `uk`
+/// and poles `pw`, returns the number of removals done.
+#[allow(clippy::too_many_arguments)]
+fn remove_knot(
+    p: usize,
+    uk: &mut Vec<Real>,
+    pw: &mut Vec<Vec4>,
+    u: Real,
+    r: usize,
+    s: usize,
+    num: usize,
+    tolerance: Real,
+) -> usize {
+    let n = pw.len() - 1;
+    let m = n + p + 1;
+    let ord = p + 1;
+    let fout = (2 * r - s - p) / 2;
+    let mut last = r - s;
+    let mut first = r - p;
+    let mut temp = vec![Vec4::ZERO; 2 * p + 1];
+    let mut t = 0;
+    while t < num {
+        let off = first - 1;
+        temp[0] = pw[off];
+        temp[last + 1 - off] = pw[last + 1];
+        let (mut i, mut j) = (first as isize, last as isize);
+        let (mut ii, mut jj) = (1isize, (last - off) as isize);
+        let ti = t as isize;
+        while j - i > ti {
+            let (iu, ju) = (i as usize, j as usize);
+            let alf_i = (u - uk[iu]) / (uk[iu + ord + t] - uk[iu]);
+            let alf_j = (u - uk[ju - t]) / (uk[ju + ord] - uk[ju - t]);
+            temp[ii as usize] = (pw[iu] - (1. - alf_i) * temp[ii as usize - 1]) / alf_i;
+            temp[jj as usize] = (pw[ju] - alf_j * temp[jj as usize + 1]) / (1. - alf_j);
+            i += 1;
+            ii += 1;
+            j -= 1;
+            jj -= 1;
+        }
+        let removable = if j - i < ti {
+            temp[ii as usize - 1].distance(temp[jj as usize + 1]) <= tolerance
+        } else {
+            let iu = i as usize;
+            let alf_i = (u - uk[iu]) / (uk[iu + ord + t] - uk[iu]);
+            let q = alf_i * temp[ii as usize + t + 1] + (1. - alf_i) * temp[ii as usize - 1];
+            pw[iu].distance(q) <= tolerance
+        };
+        if !removable {
+            break;
+        }
+        let (mut i, mut j) = (first, last);
+        while j as isize - i as isize > ti {
+            pw[i] = temp[i - off];
+            pw[j] = temp[j - off];
+            i += 1;
+            j -= 1;
+        }
+        first -= 1;
+        last += 1;
+        t += 1;
+    }
+    if t == 0 {
+        return 0;
+    }
+    for k in r + 1..=m {
+        uk[k - t] = uk[k];
+    }
+    uk.truncate(m + 1 - t);
+    let mut j = fout;
+    let mut i = j;
+    for k in 1..t {
+        if k % 2 == 1 {
+            i += 1;
+        } else {
+            j -= 1;
+        }
+    }
+    for k in i + 1..=n {
+        pw[j] = pw[k];
+        j += 1;
+    }
+    pw.truncate(n + 1 - t);
+    t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::Vec3;
+
+    fn curve(flatten: &[Real], degree: usize, weights: Option<&[Real]>) -> BSpline {
+        let nb_poles = flatten.len() - degree - 1;
+        let knots = BSplineKnots::try_from_flatten(flatten, degree, nb_poles, false).unwrap();
+        let poles = (0..nb_poles)
+            .map(|i| BSplinePole {
+                pole: Vec3::new(i as Real, (i as Real * 1.3).sin(), (i % 3) as Real),
+                weight: weights.map_or(1., |w| w[i]),
+            })
+            .collect();
+        BSpline::try_new(degree, knots, poles, weights.is_some()).unwrap()
+    }
+
+    fn periodic() -> BSpline {
+        let flatten = (0..=6).map(|v| v as Real).collect::<Vec<_>>();
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 6, true).unwrap();
+        let poles = (0..6)
+            .map(|i| {
+                let angle = (60. * i as Real).to_radians();
+                BSplinePole {
+                    pole: Vec3::new(angle.cos(), angle.sin(), 0.1 * i as Real),
+                    weight: 1.,
+                }
+            })
+            .collect();
+        BSpline::try_new(3, knots, poles, false).unwrap()
+    }
+
+    /// Largest distance between `a` and `b` at parameters across the domain of `a`.
+    fn deviation(a: &BSpline, b: &BSpline) -> Real {
+        let (lower, upper) = (a.lower_parameter(), a.upper_parameter());
+        (0..=50)
+            .map(|i| {
+                let u = lower + (upper - lower) * i as Real / 50.;
+                a.interop(u).distance(b.interop(u))
+            })
+            .fold(0., Real::max)
+    }
+
+    #[test]
+    fn insert_then_remove_knot() {
+        let flatten = [0., 0., 0., 0., 0.25, 0.5, 0.75, 1., 1., 1., 1.];
+        let weights = [1., 2., 0.5, 1., 1.5, 1., 1.];
+        for original in [curve(&flatten, 3, None), curve(&flatten, 3, Some(&weights))] {
+            let mut refined = original.clone();
+            assert_eq!(refined.insert_knot(0.4, 2), 2);
+            assert_eq!(refined.poles.len(), original.poles.len() + 2);
+            assert!(deviation(&original, &refined) < 1e-5);
+            assert_eq!(refined.remove_knot(0.4, 2, 1e-4), 2);
+            assert_eq!(refined.knots.flatten, original.knots.flatten);
+            for (p, q) in refined.poles.iter().zip(&original.poles) {
+                assert!(p.pole.distance(q.pole) < 1e-4);
+                assert!((p.weight - q.weight).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn elevate_degree_keeps_shape() {
+        let flatten = [0., 0., 0., 0.3, 0.3, 0.6, 1., 1., 1.];
+        let original = curve(&flatten, 2, Some(&[1., 0.7, 1.2, 1., 2., 1.]));
+        let mut elevated = original.clone();
+        elevated.elevate_degree(2);
+        assert_eq!(elevated.degree, 4);
+        assert!(deviation(&original, &elevated) < 1e-5);
+    }
+
+    #[test]
+    fn clamp_keeps_shape() {
+        let flatten = (0..=10).map(|v| v as Real).collect::<Vec<_>>();
+        let original = curve(&flatten, 3, None);
+        let mut clamped = original.clone();
+        clamped.clamp();
+        assert!(deviation(&original, &clamped) < 1e-5);
+        let start = original.interop(original.lower_parameter());
+        assert!(clamped.poles[0].pole.distance(start) < 1e-5);
+
+        let original = periodic();
+        assert!(deviation(&original, &original.to_non_periodic()) < 1e-5);
+        let mut clamped = original.clone();
+        clamped.clamp();
+        assert!(!clamped.is_periodic);
+        assert!(deviation(&original, &clamped) < 1e-5);
+    }
+
+    #[test]
+    fn make_compatible_nearly_coincident_knots() {
+        let d = 4. * Real::EPSILON;
+        // Distinct knots closer than rounding, together over the degree.
+        let first = curve(&[0., 0., 0., 0.5, 0.5, 0.5 + d, 1., 1., 1.], 2, None);
+        let second = curve(
+            &[0., 0., 0., 0.5 + 2. * d, 1., 1., 1.],
+            2,
+            Some(&[1., 2., 1., 1.]),
+        );
+        let mut curves = [first.clone(), second.clone()];
+        BSpline::make_compatible(&mut curves).unwrap();
+
+        assert_eq!(curves[0].knots.flatten, curves[1].knots.flatten);
+        assert_eq!(curves[0].knots.flatten, first.knots.flatten);
+        assert!(curves.iter().all(|c| c.use_rational));
+        assert!(deviation(&first, &curves[0]) < 1e-5);
+        assert!(deviation(&second, &curves[1]) < 1e-5);
+    }
+}
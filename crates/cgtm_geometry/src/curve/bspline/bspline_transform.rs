@@ -0,0 +1,178 @@
+use crate::precision::{Mat4, Quat, Real, Vec3, Vec4};
+
+use super::{bspline_error::BSplineError, bspline_poles::BSplinePole, check_pole, BSpline};
+
+impl BSpline {
+    /// Transforms the curve by transforming its poles, which is exact.
+    ///
+    /// With a projective `matrix` (last row is not `(0, 0, 0, 1)`) the poles are mapped in
+    /// homogeneous coordinates `(w * P, w)`: the resulting `w` becomes the new weight and
+    /// the curve becomes rational. Fails if a pole becomes non-finite or a weight is not
+    /// positive, the curve is then unchanged.
+    pub fn try_transform(&mut self, matrix: &Mat4) -> Result<(), BSplineError> {
+        let projective = matrix.row(3) != Vec4::W;
+        let use_rational = self.use_rational || projective;
+        let poles = self
+            .poles
+            .iter()
+            .map(|p| {
+                if !projective {
+                    return BSplinePole {
+                        pole: matrix.transform_point3(p.pole),
+                        weight: p.weight,
+                    };
+                }
+                let weight = if self.use_rational { p.weight } else { 1. };
+                let h = *matrix * (weight * p.pole).extend(weight);
+                BSplinePole {
+                    pole: h.truncate() / h.w,
+                    weight: h.w,
+                }
+            })
+            .collect::<Vec<_>>();
+        for (index, p) in poles.iter().enumerate() {
+            check_pole(index, p, use_rational)?;
+        }
+        self.poles = poles;
+        self.use_rational = use_rational;
+        Ok(())
+    }
+
+    pub fn try_translate(&mut self, offset: Vec3) -> Result<(), BSplineError> {
+        self.try_transform(&Mat4::from_translation(offset))
+    }
+
+    /// Rotation by `angle` (radians) around the axis through `origin` along `axis`.
+    pub fn try_rotate(
+        &mut self,
+        origin: Vec3,
+        axis: Vec3,
+        angle: Real,
+    ) -> Result<(), BSplineError> {
+        let axis = axis.try_normalize().ok_or(BSplineError::ZeroAxis)?;
+        let rotation = Mat4::from_quat(Quat::from_axis_angle(axis, angle));
+        self.try_transform(
+            &(Mat4::from_translation(origin) * rotation * Mat4::from_translation(-origin)),
+        )
+    }
+
+    /// Scaling by `factor` from `center`.
+    pub fn try_scale(&mut self, center: Vec3, factor: Real) -> Result<(), BSplineError> {
+        self.try_transform(
+            &(Mat4::from_translation(center)
+                * Mat4::from_scale(Vec3::splat(factor))
+                * Mat4::from_translation(-center)),
+        )
+    }
+
+    /// Reflection through the plane at `origin` with `normal`.
+    pub fn try_mirror(&mut self, origin: Vec3, normal: Vec3) -> Result<(), BSplineError> {
+        let n = normal.try_normalize().ok_or(BSplineError::ZeroNormal)?;
+        let reflection = Mat4::from_cols(
+            (Vec3::X - 2. * n.x * n).extend(0.),
+            (Vec3::Y - 2. * n.y * n).extend(0.),
+            (Vec3::Z - 2. * n.z * n).extend(0.),
+            (2. * origin.dot(n) * n).extend(1.),
+        );
+        self.try_transform(&reflection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::bspline_knots::BSplineKnots;
+    use crate::precision::consts;
+
+    fn cubic() -> BSpline {
+        let flatten = [0., 0., 0., 0., 0.5, 1., 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 5, false).unwrap();
+        let poles = (0..5)
+            .map(|i| BSplinePole {
+                pole: Vec3::new(i as Real, (i as Real).sin(), 0.3 * (i % 2) as Real),
+                weight: 1.,
+            })
+            .collect();
+        BSpline::try_new(3, knots, poles, false).unwrap()
+    }
+
+    fn arc() -> BSpline {
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 0., 1., 1., 1.], 2, 3, false);
+        let poles = [
+            (Vec3::X, 1.),
+            (Vec3::new(1., 1., 0.), Real::sqrt(0.5)),
+            (Vec3::Y, 1.),
+        ]
+        .map(|(pole, weight)| BSplinePole { pole, weight })
+        .to_vec();
+        BSpline::try_new(2, knots.unwrap(), poles, true).unwrap()
+    }
+
+    /// Largest distance between `f(original(u))` and `moved(u)` over samples.
+    fn deviation(original: &BSpline, moved: &BSpline, f: impl Fn(Vec3) -> Vec3) -> Real {
+        (0..=20)
+            .map(|i| {
+                let u = i as Real / 20.;
+                f(original.interop(u)).distance(moved.interop(u))
+            })
+            .fold(0., Real::max)
+    }
+
+    #[test]
+    fn rigid_transforms_move_points() {
+        let original = cubic();
+        let offset = Vec3::new(1., -2., 0.5);
+        let mut moved = original.clone();
+        moved.try_translate(offset).unwrap();
+        assert!(deviation(&original, &moved, |p| p + offset) < 1e-5);
+
+        let (origin, axis) = (Vec3::new(1., 0., 0.), Vec3::new(0., 0., 2.));
+        let mut moved = original.clone();
+        moved.try_rotate(origin, axis, consts::FRAC_PI_2).unwrap();
+        let rotate = |p: Vec3| origin + Vec3::new(-(p - origin).y, (p - origin).x, p.z);
+        assert!(deviation(&original, &moved, rotate) < 1e-5);
+
+        let mut moved = original.clone();
+        moved.try_mirror(Vec3::new(0., 0., 1.), Vec3::Z).unwrap();
+        assert!(deviation(&original, &moved, |p| Vec3::new(p.x, p.y, 2. - p.z)) < 1e-5);
+
+        let mut moved = original.clone();
+        moved.try_scale(origin, 2.).unwrap();
+        assert!(deviation(&original, &moved, |p| origin + 2. * (p - origin)) < 1e-5);
+    }
+
+    #[test]
+    fn perspective_of_rational_arc() {
+        let original = arc();
+        let matrix = Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::Z, Vec4::new(0., 0., 0.5, 1.))
+            * Mat4::from_cols(
+                Vec4::new(1., 0., 0., 0.3),
+                Vec4::new(0., 1., 0., 0.2),
+                Vec4::Z,
+                Vec4::W,
+            );
+        let mut moved = original.clone();
+        moved.try_transform(&matrix).unwrap();
+        assert!(moved.use_rational());
+        assert!(deviation(&original, &moved, |p| matrix.project_point3(p)) < 1e-5);
+    }
+
+    #[test]
+    fn transform_errors_keep_curve() {
+        let original = cubic();
+        let mut moved = original.clone();
+        assert!(matches!(
+            moved.try_rotate(Vec3::ZERO, Vec3::ZERO, 1.),
+            Err(BSplineError::ZeroAxis)
+        ));
+        assert!(matches!(
+            moved.try_mirror(Vec3::ZERO, Vec3::ZERO),
+            Err(BSplineError::ZeroNormal)
+        ));
+        // `w = 1 - x` vanishes at the pole with `x = 1`.
+        let projective = Mat4::from_cols(Vec4::new(1., 0., 0., -1.), Vec4::Y, Vec4::Z, Vec4::W);
+        assert!(moved.try_transform(&projective).is_err());
+        assert!(!moved.use_rational());
+        assert!(deviation(&original, &moved, |p| p) == 0.);
+    }
+}
@@ -1,67 +1,470 @@
-use super::bspline_poles::BSplinePole;
-use f3l::glam::Vec3;
+use super::bspline_poles::{BSplinePole, ControlPoint};
+use crate::precision::{Real, Vec3};
 
-pub fn de_boor(
-    u: f32,
-    knots: &[f32],
-    poles: &[BSplinePole],
+pub fn de_boor<P: ControlPoint>(
+    u: Real,
+    knots: &[Real],
+    poles: &[BSplinePole<P>],
     degree: usize,
     rational: bool,
-) -> Vec3 {
-    let mut rs = poles.to_vec();
+) -> P {
+    de_boor_in_place(u, knots, &mut poles.to_vec(), degree, rational)
+}
 
+/// Same as [`de_boor`] without allocating, `poles` is used as working space.
+pub fn de_boor_in_place<P: ControlPoint>(
+    u: Real,
+    knots: &[Real],
+    poles: &mut [BSplinePole<P>],
+    degree: usize,
+    rational: bool,
+) -> P {
     for i in 0..degree {
-        let mut rs_temp = vec![BSplinePole::default(); degree - i];
         for j in 0..degree - i {
             let a = (u - knots[j + i]) / (knots[j + degree] - knots[j + i]);
-            rs_temp[j].pole = (1. - a) * rs[j].pole + a * rs[j + 1].pole;
+            poles[j].pole = poles[j].pole * (1. - a) + poles[j + 1].pole * a;
             if rational {
-                rs_temp[j].weight = (1. - a) * rs[j].weight + a * rs[j + 1].weight;
+                poles[j].weight = (1. - a) * poles[j].weight + a * poles[j + 1].weight;
             }
         }
-        rs = rs_temp;
     }
     if rational {
-        rs[0].pole / rs[0].weight
+        poles[0].pole / poles[0].weight
     } else {
-        rs[0].pole
+        poles[0].pole
     }
 }
 
 pub fn compute_coefficients(
-    u: f32,
-    knots: &[f32],
+    u: Real,
+    knots: &[Real],
     nb_poles: usize,
     degree: usize,
     n_extend: usize,
-) -> Vec<f32> {
-    (0..nb_poles)
-        .map(|i| recursive_coe(u, knots, degree, i, n_extend))
-        .collect()
+) -> Vec<Real> {
+    let knots = &knots[n_extend..];
+    let span = find_span(u, knots, degree, nb_poles);
+    let mut coefficients = vec![0.; nb_poles];
+    basis_functions(span, u, knots, degree)
+        .into_iter()
+        .enumerate()
+        .for_each(|(j, n)| coefficients[span + j - degree] = n);
+    coefficients
 }
 
-fn recursive_coe(u: f32, knots: &[f32], p: usize, i: usize, n_extend: usize) -> f32 {
-    if p == 0 {
-        return if knots[n_extend + i] <= u && u < knots[n_extend + i + 1] {
-            1f32
+/// Span `s` with `knots[s] <= u < knots[s + 1]`, clamped to `degree..nb_poles`.
+///
+/// The last span is closed, so `u = knots[nb_poles]` is in span `nb_poles - 1`.
+pub fn find_span(u: Real, knots: &[Real], degree: usize, nb_poles: usize) -> usize {
+    let n = nb_poles - 1;
+    if u >= knots[n + 1] {
+        return n;
+    }
+    if u <= knots[degree] {
+        return degree;
+    }
+    let (mut low, mut high) = (degree, n + 1);
+    let mut mid = (low + high) / 2;
+    while u < knots[mid] || u >= knots[mid + 1] {
+        if u < knots[mid] {
+            high = mid;
         } else {
-            0f32
-        };
+            low = mid;
+        }
+        mid = (low + high) / 2;
     }
+    mid
+}
 
-    let factor1 = knots[n_extend + i + p] - knots[n_extend + i];
-    let factor2 = knots[n_extend + i + p + 1] - knots[n_extend + i + 1];
-    let n_i_p_1 = recursive_coe(u, knots, p - 1, i, n_extend);
-    let n_i_1_p_1 = recursive_coe(u, knots, p - 1, i + 1, n_extend);
-    let part1 = if factor1 == 0. {
-        0f32
-    } else {
-        (u - knots[n_extend + i]) * n_i_p_1 / factor1
-    };
-    let part2 = if factor2 == 0. {
-        0f32
+/// Non-zero basis functions `N_{span - degree}(u), ..., N_{span}(u)`, The NURBS Book A2.2.
+pub fn basis_functions(span: usize, u: Real, knots: &[Real], degree: usize) -> Vec<Real> {
+    let mut ns = vec![0.; degree + 1];
+    let mut left = vec![0.; degree + 1];
+    let mut right = vec![0.; degree + 1];
+    ns[0] = 1.;
+    for j in 1..=degree {
+        left[j] = u - knots[span + 1 - j];
+        right[j] = knots[span + j] - u;
+        let mut saved = 0.;
+        for r in 0..j {
+            let temp = ns[r] / (right[r + 1] + left[j - r]);
+            ns[r] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        ns[j] = saved;
+    }
+    ns
+}
+
+/// Derivatives `0..=order` of the non-zero basis functions, The NURBS Book A2.3.
+///
+/// `ders[k][j]` is the `k`-th derivative of `N_{span - degree + j}(u)`.
+pub fn basis_derivatives(
+    span: usize,
+    u: Real,
+    knots: &[Real],
+    degree: usize,
+    order: usize,
+) -> Vec<Vec<Real>> {
+    let p = degree;
+    let mut ndu = vec![vec![0.; p + 1]; p + 1];
+    let mut left = vec![0.; p + 1];
+    let mut right = vec![0.; p + 1];
+    ndu[0][0] = 1.;
+    for j in 1..=p {
+        left[j] = u - knots[span + 1 - j];
+        right[j] = knots[span + j] - u;
+        let mut saved = 0.;
+        for r in 0..j {
+            // Lower triangle holds the knot differences.
+            ndu[j][r] = right[r + 1] + left[j - r];
+            let temp = ndu[r][j - 1] / ndu[j][r];
+            ndu[r][j] = saved + right[r + 1] * temp;
+            saved = left[j - r] * temp;
+        }
+        ndu[j][j] = saved;
+    }
+
+    let mut ders = vec![vec![0.; p + 1]; order + 1];
+    (0..=p).for_each(|j| ders[0][j] = ndu[j][p]);
+    let n = order.min(p);
+    let mut a = [vec![0.; p + 1], vec![0.; p + 1]];
+    for r in 0..=p {
+        let (mut s1, mut s2) = (0, 1);
+        a[0][0] = 1.;
+        for k in 1..=n {
+            let mut d = 0.;
+            let rk = r as isize - k as isize;
+            let pk = p - k;
+            if rk >= 0 {
+                a[s2][0] = a[s1][0] / ndu[pk + 1][rk as usize];
+                d = a[s2][0] * ndu[rk as usize][pk];
+            }
+            let j1 = if rk >= -1 { 1 } else { (-rk) as usize };
+            let j2 = if r as isize - 1 <= pk as isize {
+                k - 1
+            } else {
+                p - r
+            };
+            for j in j1..=j2 {
+                let rkj = (rk + j as isize) as usize;
+                a[s2][j] = (a[s1][j] - a[s1][j - 1]) / ndu[pk + 1][rkj];
+                d += a[s2][j] * ndu[rkj][pk];
+            }
+            if r <= pk {
+                a[s2][k] = -a[s1][k - 1] / ndu[pk + 1][r];
+                d += a[s2][k] * ndu[r][pk];
+            }
+            ders[k][r] = d;
+            std::mem::swap(&mut s1, &mut s2);
+        }
+    }
+    let mut factor = p as Real;
+    for (k, row) in ders.iter_mut().enumerate().take(n + 1).skip(1) {
+        row.iter_mut().for_each(|d| *d *= factor);
+        factor *= (p - k) as Real;
+    }
+    ders
+}
+
+/// Solves `A x = b` for a symmetric positive definite band matrix, by Cholesky.
+///
+/// `band[i * (bandwidth + 1) + d]` holds `A[i][i - d]`, the lower band. Returns `None`
+/// when `A` is not positive definite.
+pub fn solve_banded_cholesky<P: ControlPoint>(
+    mut band: Vec<Real>,
+    bandwidth: usize,
+    mut rhs: Vec<P>,
+) -> Option<Vec<P>> {
+    let m = rhs.len();
+    let w = bandwidth + 1;
+    // Factorize in place, `A = L L^T`.
+    for i in 0..m {
+        for j in i.saturating_sub(bandwidth)..=i {
+            let mut sum = band[i * w + i - j];
+            for k in i.saturating_sub(bandwidth)..j {
+                sum -= band[i * w + i - k] * band[j * w + j - k];
+            }
+            if i == j {
+                if sum.is_nan() || sum <= Real::EPSILON * band[i * w] {
+                    return None;
+                }
+                band[i * w] = sum.sqrt();
+            } else {
+                band[i * w + i - j] = sum / band[j * w];
+            }
+        }
+    }
+    // Forward `L y = b`, then backward `L^T x = y`.
+    for i in 0..m {
+        for k in i.saturating_sub(bandwidth)..i {
+            let rk = rhs[k];
+            rhs[i] -= rk * band[i * w + i - k];
+        }
+        rhs[i] = rhs[i] / band[i * w];
+    }
+    for i in (0..m).rev() {
+        for k in i + 1..m.min(i + w) {
+            let rk = rhs[k];
+            rhs[i] -= rk * band[k * w + k - i];
+        }
+        rhs[i] = rhs[i] / band[i * w];
+    }
+    Some(rhs)
+}
+
+fn de_boor_homogeneous<P: ControlPoint>(
+    u: Real,
+    knots: &[Real],
+    poles: &[BSplinePole<P>],
+    degree: usize,
+) -> BSplinePole<P> {
+    de_boor_homogeneous_in_place(u, knots, &mut poles.to_vec(), degree)
+}
+
+/// De Boor on weighted poles `(w * P, w)`, returns the homogeneous point.
+///
+/// `poles` is used as working space.
+pub fn de_boor_homogeneous_in_place<P: ControlPoint>(
+    u: Real,
+    knots: &[Real],
+    poles: &mut [BSplinePole<P>],
+    degree: usize,
+) -> BSplinePole<P> {
+    for i in 0..degree {
+        for j in 0..degree - i {
+            let a = (u - knots[j + i]) / (knots[j + degree] - knots[j + i]);
+            poles[j].pole = poles[j].pole * (1. - a) + poles[j + 1].pole * a;
+            poles[j].weight = (1. - a) * poles[j].weight + a * poles[j + 1].weight;
+        }
+    }
+    poles[0]
+}
+
+/// Derivatives `0..=order` of the (homogeneous) span, by differencing the local poles.
+///
+/// `knots` and `poles` are the same span-local slices as for [`de_boor`].
+pub fn de_boor_derivatives<P: ControlPoint>(
+    u: Real,
+    knots: &[Real],
+    poles: &[BSplinePole<P>],
+    degree: usize,
+    order: usize,
+) -> Vec<BSplinePole<P>> {
+    let mut ders = vec![BSplinePole::default(); order + 1];
+    let mut qs = poles.to_vec();
+    for (k, der) in ders.iter_mut().enumerate() {
+        if k > degree {
+            break;
+        }
+        let q = degree - k;
+        let kn = &knots[k..knots.len() - k];
+        *der = de_boor_homogeneous(u, kn, &qs, q);
+        if q == 0 {
+            break;
+        }
+        qs = (0..q)
+            .map(|j| {
+                let d = kn[j + q] - kn[j];
+                if d == 0. {
+                    return BSplinePole::default();
+                }
+                let f = q as Real / d;
+                BSplinePole {
+                    pole: (qs[j + 1].pole - qs[j].pole) * f,
+                    weight: f * (qs[j + 1].weight - qs[j].weight),
+                }
+            })
+            .collect();
+    }
+    ders
+}
+
+/// Closest points between segments `p1q1` and `p2q2`, as `(s, t, distance)`
+/// with `s` and `t` the normalized positions on each segment.
+pub fn closest_on_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Real, Real, Real) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.dot(d1);
+    let e = d2.dot(d2);
+    let f = d2.dot(r);
+    let (s, t) = if a <= Real::EPSILON && e <= Real::EPSILON {
+        (0., 0.)
+    } else if a <= Real::EPSILON {
+        (0., (f / e).clamp(0., 1.))
     } else {
-        (knots[n_extend + i + p + 1] - u) * n_i_1_p_1 / factor2
+        let c = d1.dot(r);
+        if e <= Real::EPSILON {
+            ((-c / a).clamp(0., 1.), 0.)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let s = if denom > 0. {
+                ((b * f - c * e) / denom).clamp(0., 1.)
+            } else {
+                0.
+            };
+            let t = (b * s + f) / e;
+            if t < 0. {
+                ((-c / a).clamp(0., 1.), 0.)
+            } else if t > 1. {
+                (((b - c) / a).clamp(0., 1.), 1.)
+            } else {
+                (s, t)
+            }
+        }
     };
-    part1 + part2
+    let dist = (p1 + d1 * s).distance(p2 + d2 * t);
+    (s, t, dist)
+}
+
+/// Parameters `(s, t)` where curves `C(s)` and `D(t)` are closest, refined from
+/// `start` by Gauss-Newton on `C(s) - D(t)`.
+///
+/// `first` and `second` give the point and first derivative of each curve, `fit` maps
+/// the updated parameters back into their domains. Stops when the points are closer
+/// than `tolerance`, when the step vanishes, or after `iterations`.
+pub fn closest_on_curves(
+    first: impl Fn(Real) -> Vec<Vec3>,
+    second: impl Fn(Real) -> Vec<Vec3>,
+    fit: impl Fn(Real, Real) -> (Real, Real),
+    start: (Real, Real),
+    tolerance: Real,
+    iterations: usize,
+) -> (Real, Real) {
+    let (mut s, mut t) = start;
+    for _ in 0..iterations {
+        let cs = first(s);
+        let dt = second(t);
+        let d = cs[0] - dt[0];
+        if d.length() <= tolerance {
+            break;
+        }
+        // Normal equations of J = [C'(s), -D'(t)].
+        let (a, b) = (cs[1], -dt[1]);
+        let (aa, ab, bb) = (a.dot(a), a.dot(b), b.dot(b));
+        let det = aa * bb - ab * ab;
+        if det.abs() <= Real::EPSILON * aa * bb {
+            break;
+        }
+        let (ra, rb) = (-a.dot(d), -b.dot(d));
+        let step_s = (bb * ra - ab * rb) / det;
+        let step_t = (aa * rb - ab * ra) / det;
+        (s, t) = fit(s + step_s, t + step_t);
+        if step_s.abs() + step_t.abs() <= Real::EPSILON {
+            break;
+        }
+    }
+    (s, t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KNOTS: [Real; 9] = [0., 0., 0., 0., 0.5, 1., 1., 1., 1.];
+
+    #[test]
+    fn coefficients_at_upper_parameter() {
+        let coefficients = compute_coefficients(1., &KNOTS, 5, 3, 0);
+        assert_eq!(coefficients, vec![0., 0., 0., 0., 1.]);
+        let coefficients = compute_coefficients(0., &KNOTS, 5, 3, 0);
+        assert_eq!(coefficients, vec![1., 0., 0., 0., 0.]);
+    }
+
+    #[test]
+    fn coefficients_extend_end_spans() {
+        // Outside the domain, the polynomials of the first and last spans continue.
+        for u in [-0.25, 1.25] {
+            let coefficients = compute_coefficients(u, &KNOTS, 5, 3, 0);
+            assert!((coefficients.iter().sum::<Real>() - 1.).abs() < 1e-5);
+            let span = find_span(u, &KNOTS, 3, 5);
+            let first = span - 3;
+            let expected = basis_functions(span, u, &KNOTS, 3);
+            assert_eq!(coefficients[first..=span], expected[..]);
+        }
+        let coefficients = compute_coefficients(1.25, &KNOTS, 5, 3, 0);
+        // `N_4(u) = ((u - 0.5) / 0.5)^3` on the last span.
+        assert!((coefficients[4] - 1.5 * 1.5 * 1.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn basis_derivatives_match_finite_differences() {
+        let h = 1e-3;
+        for u in [0.1, 0.3, 0.7, 0.9] {
+            let span = find_span(u, &KNOTS, 3, 5);
+            let ders = basis_derivatives(span, u, &KNOTS, 3, 4);
+            assert_eq!(ders[0], basis_functions(span, u, &KNOTS, 3));
+            // Functions sum to 1, so their derivatives sum to 0.
+            for row in &ders[1..] {
+                assert!(row.iter().sum::<Real>().abs() < 1e-3);
+            }
+            // Above the degree, derivatives vanish.
+            assert!(ders[4].iter().all(|&d| d == 0.));
+            let (before, after) = (
+                basis_derivatives(span, u - h, &KNOTS, 3, 3),
+                basis_derivatives(span, u + h, &KNOTS, 3, 3),
+            );
+            for k in 1..=3 {
+                for j in 0..=3 {
+                    let difference = (after[k - 1][j] - before[k - 1][j]) / (2. * h);
+                    assert!((ders[k][j] - difference).abs() < 1e-2 * (1. + ders[k][j].abs()));
+                }
+            }
+        }
+    }
+
+    /// Lower band of width 2 of a diagonally dominant matrix, with its dense form.
+    fn band_matrix(m: usize) -> (Vec<Real>, na::DMatrix<Real>) {
+        let entry = |i: usize, j: usize| match i.abs_diff(j) {
+            0 => 4. + 0.1 * i as Real,
+            1 => -1.,
+            2 => 0.5,
+            _ => 0.,
+        };
+        let band = (0..m)
+            .flat_map(|i| (0..3).map(move |d| if d <= i { entry(i, i - d) } else { 0. }))
+            .collect();
+        (band, na::DMatrix::from_fn(m, m, entry))
+    }
+
+    #[test]
+    fn banded_cholesky_matches_dense_solve() {
+        let m = 7;
+        let (band, dense) = band_matrix(m);
+        let rhs = (0..m).map(|i| (i as Real * 0.9).sin()).collect::<Vec<_>>();
+        let solution = solve_banded_cholesky(band, 2, rhs.clone()).unwrap();
+        let expected = dense.lu().solve(&na::DVector::from_vec(rhs)).unwrap();
+        for (x, e) in solution.iter().zip(expected.iter()) {
+            assert!((x - e).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn banded_cholesky_rejects_singular() {
+        let (mut band, _) = band_matrix(5);
+        // Row and column 2 zeroed, the matrix is singular.
+        for (i, d) in [(2, 0), (2, 1), (2, 2), (3, 1), (4, 2)] {
+            band[i * 3 + d] = 0.;
+        }
+        assert!(solve_banded_cholesky(band, 2, vec![1.; 5]).is_none());
+        let (mut band, _) = band_matrix(5);
+        band[3 * 3] = -4.;
+        assert!(solve_banded_cholesky(band, 2, vec![1.; 5]).is_none());
+    }
+
+    #[test]
+    fn closest_on_crossing_curves() {
+        // Line along x and parabola touching it, meeting at `s = 1`, `t = 0.5`.
+        let line = |s: Real| vec![Vec3::new(s, 0., 0.), Vec3::X];
+        let parabola = |t: Real| {
+            let y = t - 0.5;
+            vec![Vec3::new(1., y, y * y), Vec3::new(0., 1., 2. * y)]
+        };
+        let clamp = |s: Real, t: Real| (s.clamp(0., 2.), t.clamp(0., 1.));
+        let (s, t) = closest_on_curves(line, parabola, clamp, (0.2, 0.9), 0., 32);
+        assert!((s - 1.).abs() < 1e-5);
+        assert!((t - 0.5).abs() < 1e-5);
+    }
 }
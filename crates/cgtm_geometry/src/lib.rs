@@ -1,3 +1,5 @@
 extern crate nalgebra as na;
 
 pub mod curve;
+pub mod precision;
+pub mod surface;
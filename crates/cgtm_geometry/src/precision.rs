@@ -0,0 +1,20 @@
+//! Floating point precision of the geometry kernel.
+//!
+//! `f32` by default, `f64` with the `f64` feature. Vector types keep the glam names,
+//! mapped to their `D*` counterparts in `f64`.
+
+#[cfg(not(feature = "f64"))]
+pub use f3l::glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+/// Constants of [`Real`], such as `PI` and `TAU`.
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+
+#[cfg(feature = "f64")]
+pub use f3l::glam::{DMat4 as Mat4, DQuat as Quat, DVec2 as Vec2, DVec3 as Vec3, DVec4 as Vec4};
+#[cfg(feature = "f64")]
+pub type Real = f64;
+/// Constants of [`Real`], such as `PI` and `TAU`.
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
@@ -0,0 +1 @@
+pub mod bspline_surface;
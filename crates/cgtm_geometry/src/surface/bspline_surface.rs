@@ -0,0 +1,415 @@
+use crate::curve::bspline::{
+    bspline_domain::BSplineDomain,
+    bspline_knots::BSplineKnots,
+    bspline_poles::{BSplinePole, ControlPoint},
+    bspline_refinement::binomial,
+    utils::{de_boor_derivatives, de_boor_homogeneous_in_place},
+};
+use crate::precision::{Real, Vec3};
+use bspline_surface_error::BSplineSurfaceError;
+
+pub mod bspline_surface_approximation;
+pub mod bspline_surface_construction;
+pub mod bspline_surface_coons;
+pub mod bspline_surface_curvature;
+pub mod bspline_surface_error;
+pub mod bspline_surface_fitting;
+pub mod bspline_surface_gordon;
+pub mod bspline_surface_interpolation;
+pub mod bspline_surface_iso;
+pub mod bspline_surface_loft;
+pub mod bspline_surface_refinement;
+pub mod bspline_surface_sweep;
+
+/// Tensor-product B-spline surface, `P` is the type of its poles, 3D by default.
+///
+/// Pole `(i, j)` is the `i`-th along u and the `j`-th along v. Each direction has its
+/// own degree and knots, and can be periodic.
+#[derive(Debug, Clone)]
+pub struct BSplineSurface<P = Vec3> {
+    pub(crate) degree_u: usize,
+    pub(crate) degree_v: usize,
+    pub(crate) knots_u: BSplineKnots,
+    pub(crate) knots_v: BSplineKnots,
+    /// Row-major, `poles[i * nb_poles_v + j]`.
+    pub(crate) poles: Vec<BSplinePole<P>>,
+    pub(crate) nb_poles_u: usize,
+    pub(crate) nb_poles_v: usize,
+    pub(crate) use_rational: bool,
+}
+
+impl<P: ControlPoint> BSplineSurface<P> {
+    /// Surface from rows of poles, `poles[i][j]` is pole `(i, j)`.
+    ///
+    /// Each direction is periodic if its knots are. Fails if the knots were not built for
+    /// the degree and number of poles of their direction, if rows differ in length, if a
+    /// pole is not finite, or if rational with a weight that is not finite and positive.
+    pub fn try_new(
+        degree_u: usize,
+        degree_v: usize,
+        knots_u: BSplineKnots,
+        knots_v: BSplineKnots,
+        poles: Vec<Vec<BSplinePole<P>>>,
+        use_rational: bool,
+    ) -> Result<Self, BSplineSurfaceError> {
+        let nb_poles_u = poles.len();
+        let nb_poles_v = poles.first().map_or(0, |row| row.len());
+        knots_u
+            .check_for(degree_u, nb_poles_u)
+            .map_err(BSplineSurfaceError::U)?;
+        knots_v
+            .check_for(degree_v, nb_poles_v)
+            .map_err(BSplineSurfaceError::V)?;
+        for (i, row) in poles.iter().enumerate() {
+            if row.len() != nb_poles_v {
+                return Err(BSplineSurfaceError::NotRectangular {
+                    row: i,
+                    expected: nb_poles_v,
+                    actual: row.len(),
+                });
+            }
+            for (j, p) in row.iter().enumerate() {
+                check_pole(i, j, p, use_rational)?;
+            }
+        }
+        Ok(Self {
+            degree_u,
+            degree_v,
+            knots_u,
+            knots_v,
+            poles: poles.into_iter().flatten().collect(),
+            nb_poles_u,
+            nb_poles_v,
+            use_rational,
+        })
+    }
+
+    pub fn degree_u(&self) -> usize {
+        self.degree_u
+    }
+
+    pub fn degree_v(&self) -> usize {
+        self.degree_v
+    }
+
+    pub fn knots_u(&self) -> &BSplineKnots {
+        &self.knots_u
+    }
+
+    pub fn knots_v(&self) -> &BSplineKnots {
+        &self.knots_v
+    }
+
+    pub fn nb_poles_u(&self) -> usize {
+        self.nb_poles_u
+    }
+
+    pub fn nb_poles_v(&self) -> usize {
+        self.nb_poles_v
+    }
+
+    pub fn pole(&self, i: usize, j: usize) -> &BSplinePole<P> {
+        &self.poles[i * self.nb_poles_v + j]
+    }
+
+    /// Replaces pole `(i, j)`, checked like the poles of [`BSplineSurface::try_new`].
+    pub fn try_set_pole(
+        &mut self,
+        i: usize,
+        j: usize,
+        pole: BSplinePole<P>,
+    ) -> Result<(), BSplineSurfaceError> {
+        if i >= self.nb_poles_u || j >= self.nb_poles_v {
+            return Err(BSplineSurfaceError::PoleIndexOutOfRange {
+                i,
+                j,
+                nb_poles_u: self.nb_poles_u,
+                nb_poles_v: self.nb_poles_v,
+            });
+        }
+        check_pole(i, j, &pole, self.use_rational)?;
+        self.poles[i * self.nb_poles_v + j] = pole;
+        Ok(())
+    }
+
+    /// All poles, row-major: pole `(i, j)` is at `i * nb_poles_v + j`.
+    pub fn poles(&self) -> &[BSplinePole<P>] {
+        &self.poles
+    }
+
+    pub fn is_periodic_u(&self) -> bool {
+        self.knots_u.is_periodic()
+    }
+
+    pub fn is_periodic_v(&self) -> bool {
+        self.knots_v.is_periodic()
+    }
+
+    pub fn use_rational(&self) -> bool {
+        self.use_rational
+    }
+
+    pub fn domain_u(&self) -> BSplineDomain {
+        BSplineDomain {
+            lower: self.knots_u.lower_value(),
+            upper: self.knots_u.upper_value(),
+        }
+    }
+
+    pub fn domain_v(&self) -> BSplineDomain {
+        BSplineDomain {
+            lower: self.knots_v.lower_value(),
+            upper: self.knots_v.upper_value(),
+        }
+    }
+
+    /// Pole `(i, j)` as `(w * P, w)`, `w = 1` when not rational.
+    pub(crate) fn weighted_pole(&self, i: usize, j: usize) -> BSplinePole<P> {
+        let p = self.pole(i, j);
+        if self.use_rational {
+            BSplinePole {
+                pole: p.pole * p.weight,
+                weight: p.weight,
+            }
+        } else {
+            BSplinePole {
+                pole: p.pole,
+                weight: 1.,
+            }
+        }
+    }
+
+    /// Span knots in u and v, and the `(degree_u + 1) x (degree_v + 1)` weighted poles
+    /// of the span of `(u, v)`, row-major. Periodic directions wrap around the seam.
+    pub(crate) fn local_span(&self, u: Real, v: Real) -> (&[Real], &[Real], Vec<BSplinePole<P>>) {
+        let (p, q) = (self.degree_u, self.degree_v);
+        let (periodic_u, periodic_v) = (self.is_periodic_u(), self.is_periodic_v());
+        let ku = self.knots_u.get_span_index(u);
+        let kv = self.knots_v.get_span_index(v);
+        let iu = self.knots_u.get_pole_index(ku, p, periodic_u);
+        let iv = self.knots_v.get_pole_index(kv, q, periodic_v);
+        let poles = (0..=p)
+            .flat_map(|a| {
+                let i = (iu + a) % self.nb_poles_u;
+                (0..=q).map(move |b| self.weighted_pole(i, (iv + b) % self.nb_poles_v))
+            })
+            .collect();
+        (
+            self.knots_u.get_knots_bounds_slice(ku, p, periodic_u),
+            self.knots_v.get_knots_bounds_slice(kv, q, periodic_v),
+            poles,
+        )
+    }
+
+    /// Point `S(u, v)`, by de Boor along v on each row of the span, then along u.
+    ///
+    /// Parameters of a periodic direction are wrapped by the period, outside the domain
+    /// of other directions they extend the first or last span.
+    pub fn interop(&self, u: Real, v: Real) -> P {
+        let (p, q) = (self.degree_u, self.degree_v);
+        let (u, v) = (
+            periodic_parameter(u, &self.knots_u),
+            periodic_parameter(v, &self.knots_v),
+        );
+        let (knots_u, knots_v, mut poles) = self.local_span(u, v);
+        let mut column = poles
+            .chunks_mut(q + 1)
+            .map(|row| de_boor_homogeneous_in_place(v, knots_v, row, q))
+            .collect::<Vec<_>>();
+        let s = de_boor_homogeneous_in_place(u, knots_u, &mut column, p);
+        if self.use_rational {
+            s.pole / s.weight
+        } else {
+            s.pole
+        }
+    }
+
+    /// Partial derivatives `ders[k][l]` of `S` by `u` `k` times and by `v` `l` times,
+    /// for `k + l <= order`, the others are zero.
+    ///
+    /// Parameters outside the domains are handled like [`BSplineSurface::interop`].
+    pub fn derivatives(&self, u: Real, v: Real, order: usize) -> Vec<Vec<P>> {
+        let (p, q) = (self.degree_u, self.degree_v);
+        let (u, v) = (
+            periodic_parameter(u, &self.knots_u),
+            periodic_parameter(v, &self.knots_v),
+        );
+        let (knots_u, knots_v, poles) = self.local_span(u, v);
+        let rows = poles
+            .chunks(q + 1)
+            .map(|row| de_boor_derivatives(v, knots_v, row, q, order))
+            .collect::<Vec<_>>();
+        // Homogeneous derivatives, `aw[k][l]`.
+        let mut aw = vec![vec![BSplinePole::default(); order + 1]; order + 1];
+        for l in 0..=order {
+            let column = rows.iter().map(|row| row[l]).collect::<Vec<_>>();
+            let ders = de_boor_derivatives(u, knots_u, &column, p, order - l);
+            for (k, d) in ders.into_iter().enumerate() {
+                aw[k][l] = d;
+            }
+        }
+        let mut skl = vec![vec![P::ZERO; order + 1]; order + 1];
+        for k in 0..=order {
+            for l in 0..=order - k {
+                if !self.use_rational {
+                    skl[k][l] = aw[k][l].pole;
+                    continue;
+                }
+                // Quotient rule, The NURBS Book A4.4.
+                let mut v = aw[k][l].pole;
+                for j in 1..=l {
+                    v -= skl[k][l - j] * (binomial(l, j) * aw[0][j].weight);
+                }
+                for i in 1..=k {
+                    v -= skl[k - i][l] * (binomial(k, i) * aw[i][0].weight);
+                    let mut v2 = P::ZERO;
+                    for j in 1..=l {
+                        v2 += skl[k - i][l - j] * (binomial(l, j) * aw[i][j].weight);
+                    }
+                    v -= v2 * binomial(k, i);
+                }
+                skl[k][l] = v / aw[0][0].weight;
+            }
+        }
+        skl
+    }
+}
+
+/// `t` shifted into the domain of `knots` by the period when periodic, unchanged
+/// otherwise.
+pub(crate) fn periodic_parameter(t: Real, knots: &BSplineKnots) -> Real {
+    let domain = BSplineDomain {
+        lower: knots.lower_value(),
+        upper: knots.upper_value(),
+    };
+    if knots.is_periodic() && t.is_finite() && !domain.contains(t) {
+        domain.wrap(t)
+    } else {
+        t
+    }
+}
+
+/// Average of the parameters generated for each sequence of points, `uniform` if none
+/// gives parameters.
+///
+/// Sequences with non-finite parameters are skipped, such as the points of a collapsed
+/// row under chord length.
+pub(crate) fn average_parameters(
+    generated: impl IntoIterator<Item = Vec<Real>>,
+    uniform: Vec<Real>,
+) -> Vec<Real> {
+    let mut params = vec![0.; uniform.len()];
+    let mut nb_sequences = 0;
+    for sequence in generated {
+        if sequence.iter().all(|u| u.is_finite()) {
+            sequence
+                .into_iter()
+                .zip(params.iter_mut())
+                .for_each(|(u, p)| *p += u);
+            nb_sequences += 1;
+        }
+    }
+    if nb_sequences == 0 {
+        return uniform;
+    }
+    params.iter_mut().for_each(|p| *p /= nb_sequences as Real);
+    params
+}
+
+/// Fails if pole `(i, j)` is not finite, or if rational with a weight that is not finite
+/// and positive.
+fn check_pole<P: ControlPoint>(
+    i: usize,
+    j: usize,
+    p: &BSplinePole<P>,
+    use_rational: bool,
+) -> Result<(), BSplineSurfaceError> {
+    if (0..P::DIM).any(|k| !p.pole.coord(k).is_finite()) {
+        return Err(BSplineSurfaceError::NonFinitePole { i, j });
+    }
+    if use_rational && !(p.weight.is_finite() && p.weight > 0.) {
+        return Err(BSplineSurfaceError::NonPositiveWeight {
+            i,
+            j,
+            weight: p.weight,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_pole_checked() {
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).unwrap();
+        let poles = [[Vec3::ZERO, Vec3::Y], [Vec3::X, Vec3::new(1., 1., 0.)]]
+            .map(|row| row.map(|pole| BSplinePole { pole, weight: 1. }).to_vec())
+            .to_vec();
+        let mut surface = BSplineSurface::try_new(1, 1, knots.clone(), knots, poles, true).unwrap();
+        let pole = BSplinePole {
+            pole: Vec3::Z,
+            weight: 2.,
+        };
+        surface.try_set_pole(1, 0, pole).unwrap();
+        assert_eq!(surface.pole(1, 0).pole, Vec3::Z);
+        assert!(matches!(
+            surface.try_set_pole(0, 2, pole),
+            Err(BSplineSurfaceError::PoleIndexOutOfRange { i: 0, j: 2, .. })
+        ));
+        let nan = BSplinePole {
+            pole: Vec3::new(0., Real::INFINITY, 0.),
+            weight: 1.,
+        };
+        assert!(matches!(
+            surface.try_set_pole(1, 1, nan),
+            Err(BSplineSurfaceError::NonFinitePole { i: 1, j: 1 })
+        ));
+        let negative = BSplinePole {
+            pole: Vec3::X,
+            weight: -1.,
+        };
+        assert!(matches!(
+            surface.try_set_pole(0, 1, negative),
+            Err(BSplineSurfaceError::NonPositiveWeight { i: 0, j: 1, .. })
+        ));
+        assert_eq!(surface.pole(0, 1).pole, Vec3::Y);
+    }
+
+    #[test]
+    fn periodic_parameters_wrap() {
+        // Closed cubic along u, linear along v.
+        let flatten = (0..=5).map(|i| i as Real / 5.).collect::<Vec<_>>();
+        let knots_u = BSplineKnots::try_from_flatten(&flatten, 3, 5, true).unwrap();
+        let knots_v = BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).unwrap();
+        let poles = (0..5)
+            .map(|i| {
+                let a = i as Real * 1.3;
+                let pole = Vec3::new(a.cos(), a.sin(), 0.2 * i as Real);
+                vec![
+                    BSplinePole { pole, weight: 1. },
+                    BSplinePole {
+                        pole: pole + Vec3::Z,
+                        weight: 1. + 0.1 * i as Real,
+                    },
+                ]
+            })
+            .collect();
+        let surface = BSplineSurface::try_new(3, 1, knots_u, knots_v, poles, true).unwrap();
+        for (u, v) in [(0.1, 0.3), (0.5, 0.9), (0.95, 0.)] {
+            let ders = surface.derivatives(u, v, 2);
+            for shift in [-2., -1., 1., 3.] {
+                assert!(surface.interop(u + shift, v).distance(ders[0][0]) < 1e-4);
+                let shifted = surface.derivatives(u + shift, v, 2);
+                for (row, shifted_row) in ders.iter().zip(&shifted) {
+                    for (d, s) in row.iter().zip(shifted_row) {
+                        assert!(d.distance(*s) < 1e-3 * (1. + d.length()));
+                    }
+                }
+                let iso = surface.iso_curve_u(u + shift);
+                assert!(iso.interop(v).distance(ders[0][0]) < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use crate::curve::bspline::{
+    bspline_approximation::BSplineApproximation, bspline_interpolation::BSplineInterpolation,
+    bspline_poles::ControlPoint, BSpline,
+};
+use crate::precision::Real;
+
+use super::{
+    average_parameters, bspline_surface_error::BSplineSurfaceApproximationError, BSplineSurface,
+};
+
+/// Least-squares fit of a surface to a grid of points, one `BSplineApproximation` per
+/// direction.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceApproximation {
+    pub u: BSplineApproximation,
+    pub v: BSplineApproximation,
+}
+
+impl BSplineSurfaceApproximation {
+    /// Fits `data[i][j]`, the `i`-th point along u and the `j`-th along v.
+    ///
+    /// Parameters of each direction are generated per row (or column) by its
+    /// `data_knots_algo`, mapped to `[0, 1]`, then averaged. Columns are fitted along u first, then the
+    /// resulting poles along v, The NURBS Book 9.4.3.
+    pub fn try_approximate<P: ControlPoint>(
+        &self,
+        data: &[Vec<P>],
+    ) -> Result<BSplineSurface<P>, BSplineSurfaceApproximationError> {
+        let nb_v = data.first().map_or(0, |row| row.len());
+        if let Some((row, r)) = data.iter().enumerate().find(|(_, r)| r.len() != nb_v) {
+            return Err(BSplineSurfaceApproximationError::NotRectangular {
+                row,
+                expected: nb_v,
+                actual: r.len(),
+            });
+        }
+        if data.is_empty() || nb_v == 0 {
+            return Err(BSplineSurfaceApproximationError::EmptyGrid);
+        }
+        let columns = (0..nb_v)
+            .map(|j| data.iter().map(|row| row[j]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let us = sequence_parameters(&self.u, &columns);
+        let vs = sequence_parameters(&self.v, data);
+
+        let along_u = columns
+            .iter()
+            .map(|column| self.u.try_approximate_with_parameters(column, &us))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BSplineSurfaceApproximationError::U)?;
+        let nb_poles_u = along_u[0].poles.len();
+        let along_v = (0..nb_poles_u)
+            .map(|i| {
+                let row = along_u.iter().map(|c| c.poles[i].pole).collect::<Vec<_>>();
+                self.v.try_approximate_with_parameters(&row, &vs)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(BSplineSurfaceApproximationError::V)?;
+
+        let degree_u = along_u[0].degree;
+        let degree_v = along_v[0].degree;
+        let knots_u = along_u[0].knots.clone();
+        let knots_v = along_v[0].knots.clone();
+        let poles = along_v.into_iter().map(|c: BSpline<P>| c.poles).collect();
+        Ok(BSplineSurface::try_new(
+            degree_u, degree_v, knots_u, knots_v, poles, false,
+        )?)
+    }
+}
+
+/// Parameters generated for each sequence of `data`, mapped to `[0, 1]` and averaged,
+/// see [`average_parameters`].
+fn sequence_parameters<P: ControlPoint>(
+    approximation: &BSplineApproximation,
+    data: &[Vec<P>],
+) -> Vec<Real> {
+    let interpolation = BSplineInterpolation {
+        degree: approximation.degree,
+        data_knots_algo: approximation.data_knots_algo,
+    };
+    let nb_params = data[0].len();
+    let uniform = (0..nb_params)
+        .map(|k| k as Real / (nb_params - 1).max(1) as Real)
+        .collect();
+    average_parameters(
+        data.iter()
+            .map(|sequence| interpolation.normalized_parameters(sequence)),
+        uniform,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{
+        bspline_data_knots::BSplineDataKnotsAlgo,
+        bspline_knots::{BSplineFeature, BSplineKnotsAlgo},
+    };
+    use crate::precision::Vec3;
+
+    #[test]
+    fn approximate_collapsed_row() {
+        // Planar fan, the first row collapses to the origin.
+        let data = (0..6)
+            .map(|i| {
+                (0..8)
+                    .map(|j| Vec3::new(1., j as Real / 7., 0.) * (i as Real / 5.))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let approximation = BSplineApproximation {
+            degree: 3,
+            nb_poles: 4,
+            feature: BSplineFeature::ClampAll,
+            knots_algo: BSplineKnotsAlgo::Uniform(4),
+            data_knots_algo: BSplineDataKnotsAlgo::ChordLength,
+        };
+        let surface = BSplineSurfaceApproximation {
+            u: approximation.clone(),
+            v: approximation,
+        }
+        .try_approximate(&data)
+        .unwrap();
+        // Chord length parameters of the other rows and columns are uniform, and the
+        // bilinear fan is reproduced.
+        for (i, row) in data.iter().enumerate() {
+            for (j, point) in row.iter().enumerate() {
+                let (u, v) = (i as Real / 5., j as Real / 7.);
+                assert!(surface.interop(u, v).distance(*point) < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn approximate_uses_end_to_end_parameters() {
+        // Biquadratic grid, reproduced by cubics at parameters `k / (n - 1)`.
+        let grid = |n_u: usize, n_v: usize, f: fn(Real, Real) -> Vec3| {
+            (0..n_u)
+                .map(|i| {
+                    (0..n_v)
+                        .map(|j| f(i as Real / (n_u - 1) as Real, j as Real / (n_v - 1) as Real))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let approximation = |data_knots_algo| BSplineApproximation {
+            degree: 3,
+            nb_poles: 5,
+            feature: BSplineFeature::ClampAll,
+            knots_algo: BSplineKnotsAlgo::Uniform(5),
+            data_knots_algo,
+        };
+        let check = |data: &[Vec<Vec3>], data_knots_algo| {
+            let surface = BSplineSurfaceApproximation {
+                u: approximation(data_knots_algo),
+                v: approximation(data_knots_algo),
+            }
+            .try_approximate(data)
+            .unwrap();
+            let (n_u, n_v) = (data.len(), data[0].len());
+            for (i, row) in data.iter().enumerate() {
+                for (j, point) in row.iter().enumerate() {
+                    let (u, v) = (i as Real / (n_u - 1) as Real, j as Real / (n_v - 1) as Real);
+                    assert!(surface.interop(u, v).distance(*point) < 1e-4);
+                }
+            }
+        };
+        let surface = |u: Real, v: Real| Vec3::new(u, v, u * u - 0.5 * u * v + v * v);
+        check(&grid(7, 9, surface), BSplineDataKnotsAlgo::Uniform);
+    }
+}
@@ -0,0 +1,220 @@
+use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole, BSpline};
+use crate::precision::{consts, Real, Vec3};
+
+use super::{bspline_surface_error::BSplineSurfaceConstructionError, BSplineSurface};
+
+/// Knots of a line along v, degree 1 on `[0, 1]`.
+fn linear_knots() -> BSplineKnots {
+    BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).expect("Linear knots.")
+}
+
+impl BSplineSurface {
+    /// Surface joining each point of `first` to the point of `second` with the same
+    /// parameter by a line along v.
+    ///
+    /// Curves are made compatible first, see [`BSpline::make_compatible`], so u runs on
+    /// `[0, 1]` along both.
+    pub fn try_ruled(
+        first: &BSpline,
+        second: &BSpline,
+    ) -> Result<Self, BSplineSurfaceConstructionError> {
+        let mut curves = [first.clone(), second.clone()];
+        BSpline::make_compatible(&mut curves)?;
+        let [first, second] = curves;
+        let poles = first
+            .poles
+            .iter()
+            .zip(&second.poles)
+            .map(|(&a, &b)| vec![a, b])
+            .collect();
+        Ok(Self::try_new(
+            first.degree,
+            1,
+            first.knots,
+            linear_knots(),
+            poles,
+            first.use_rational,
+        )?)
+    }
+
+    /// Surface swept by `curve` translated along `direction`, `S(u, v) = C(u) + v D`.
+    ///
+    /// u keeps the knots of the curve, periodic included.
+    pub fn try_extrusion(
+        curve: &BSpline,
+        direction: Vec3,
+    ) -> Result<Self, BSplineSurfaceConstructionError> {
+        if direction.length_squared() == 0. || !direction.is_finite() {
+            return Err(BSplineSurfaceConstructionError::ZeroDirection);
+        }
+        let poles = curve
+            .poles
+            .iter()
+            .map(|&p| {
+                let moved = BSplinePole {
+                    pole: p.pole + direction,
+                    weight: p.weight,
+                };
+                vec![p, moved]
+            })
+            .collect();
+        Ok(Self::try_new(
+            curve.degree,
+            1,
+            curve.knots.clone(),
+            linear_knots(),
+            poles,
+            curve.use_rational,
+        )?)
+    }
+
+    /// Surface swept by `curve` rotated by `angle` about the axis through `origin` along
+    /// `axis`, counterclockwise around `axis`, The NURBS Book A8.1.
+    ///
+    /// u keeps the knots of the curve, v is a rational quadratic circle on `[0, 1]`, one
+    /// arc per quarter turn at most.
+    pub fn try_revolution(
+        curve: &BSpline,
+        origin: Vec3,
+        axis: Vec3,
+        angle: Real,
+    ) -> Result<Self, BSplineSurfaceConstructionError> {
+        let axis = axis
+            .try_normalize()
+            .ok_or(BSplineSurfaceConstructionError::ZeroAxis)?;
+        if !(angle > 0. && angle <= consts::TAU) {
+            return Err(BSplineSurfaceConstructionError::AngleOutOfRange { angle });
+        }
+        let nb_arcs = (angle / consts::FRAC_PI_2 - Real::EPSILON).ceil().max(1.) as usize;
+        let delta = angle / nb_arcs as Real;
+        let middle_weight = (delta / 2.).cos();
+
+        let mut flatten = vec![0.; 3];
+        for i in 1..nb_arcs {
+            let knot = i as Real / nb_arcs as Real;
+            flatten.extend([knot, knot]);
+        }
+        flatten.extend([1.; 3]);
+        let knots_v = BSplineKnots::try_from_flatten(&flatten, 2, 2 * nb_arcs + 1, false)?;
+
+        let poles = curve
+            .poles
+            .iter()
+            .map(|p| {
+                let weight = if curve.use_rational { p.weight } else { 1. };
+                let center = origin + (p.pole - origin).dot(axis) * axis;
+                let x = p.pole - center;
+                let y = axis.cross(x);
+                // Poles at the arc ends are on the circle, the middle ones at the corner
+                // of their tangents, `1 / cos(delta / 2)` farther.
+                (0..=2 * nb_arcs)
+                    .map(|k| {
+                        let theta = k as Real * delta / 2.;
+                        let (w, scale) = if k % 2 == 0 {
+                            (1., 1.)
+                        } else {
+                            (middle_weight, 1. / middle_weight)
+                        };
+                        BSplinePole {
+                            pole: center + scale * (theta.cos() * x + theta.sin() * y),
+                            weight: weight * w,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self::try_new(
+            curve.degree,
+            2,
+            curve.knots.clone(),
+            knots_v,
+            poles,
+            true,
+        )?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cubic in the `(x, z)` plane, away from the z axis, with a knot at `0.5`.
+    fn profile() -> BSpline {
+        let flatten = [0., 0., 0., 0., 0.5, 1., 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 5, false).unwrap();
+        let poles = [(1., 0.), (2., 0.5), (1.5, 1.), (2.5, 1.5), (1., 2.)]
+            .map(|(x, z)| BSplinePole {
+                pole: Vec3::new(x, 0., z),
+                weight: 1.,
+            })
+            .to_vec();
+        BSpline::try_new(3, knots, poles, false).unwrap()
+    }
+
+    fn samples() -> impl Iterator<Item = (Real, Real)> {
+        (0..=10).flat_map(|i| (0..=10).map(move |j| (i as Real / 10., j as Real / 10.)))
+    }
+
+    #[test]
+    fn revolution_keeps_radius() {
+        let curve = profile();
+        for angle in [consts::TAU, 2., consts::FRAC_PI_2] {
+            let surface =
+                BSplineSurface::try_revolution(&curve, Vec3::ZERO, Vec3::Z, angle).unwrap();
+            for i in 0..=10 {
+                let u = i as Real / 10.;
+                let p = curve.interop(u);
+                // Counterclockwise angle turned since `v = 0`, summed over small steps.
+                let mut turned = 0.;
+                let mut previous = Vec3::new(p.x, 0., p.z);
+                for j in 0..=40 {
+                    let q = surface.interop(u, j as Real / 40.);
+                    assert!((q.z - p.z).abs() < 1e-5);
+                    assert!((q.truncate().length() - p.x).abs() < 1e-5);
+                    let step = previous
+                        .cross(q)
+                        .z
+                        .atan2(previous.truncate().dot(q.truncate()));
+                    assert!(step >= -1e-5);
+                    turned += step;
+                    previous = q;
+                }
+                assert!((turned - angle).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn extrusion_and_ruled_are_linear_in_v() {
+        let curve = profile();
+        let direction = Vec3::new(0.5, 1., 0.);
+        let extrusion = BSplineSurface::try_extrusion(&curve, direction).unwrap();
+        let mut moved = curve.clone();
+        moved.try_translate(direction).unwrap();
+        let ruled = BSplineSurface::try_ruled(&curve, &moved).unwrap();
+        for (u, v) in samples() {
+            let exact = curve.interop(u) + v * direction;
+            assert!(extrusion.interop(u, v).distance(exact) < 1e-5);
+            assert!(ruled.interop(u, v).distance(exact) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn construction_errors() {
+        let curve = profile();
+        assert!(matches!(
+            BSplineSurface::try_extrusion(&curve, Vec3::ZERO),
+            Err(BSplineSurfaceConstructionError::ZeroDirection)
+        ));
+        assert!(matches!(
+            BSplineSurface::try_revolution(&curve, Vec3::ZERO, Vec3::ZERO, 1.),
+            Err(BSplineSurfaceConstructionError::ZeroAxis)
+        ));
+        for angle in [0., -1., consts::TAU + 0.1, Real::NAN] {
+            assert!(matches!(
+                BSplineSurface::try_revolution(&curve, Vec3::ZERO, Vec3::Z, angle),
+                Err(BSplineSurfaceConstructionError::AngleOutOfRange { .. })
+            ));
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole, BSpline};
+use crate::precision::Real;
+
+use super::{
+    bspline_surface_error::{BSplineSurfaceCoonsError, BSplineSurfaceError},
+    BSplineSurface,
+};
+
+/// Blending of the boundaries across a Coons patch.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BSplineCoonsBlending {
+    /// Linear blending, `1 - t` and `t`.
+    #[default]
+    Bilinear,
+    /// Cubic Hermite blending, `1 - 3t^2 + 2t^3` and `3t^2 - 2t^3`, so the patch leaves
+    /// each boundary along its opposite one.
+    Bicubic,
+}
+
+/// Patch filling four boundary curves, The NURBS Book 10.6.
+///
+/// The patch is the boolean sum of the surfaces blending opposite boundaries, minus
+/// the one blending the corners, computed on homogeneous poles. Boundaries are exact
+/// where curves meeting at a corner have the same weight there, as non-rational curves
+/// and usual conic arcs.
+#[derive(Debug, Clone, Copy)]
+pub struct BSplineSurfaceCoons {
+    pub blending: BSplineCoonsBlending,
+    /// Largest gap allowed between curves at a corner.
+    pub tolerance: Real,
+}
+
+impl BSplineSurfaceCoons {
+    /// Patch bounded by `u_curves`, `S(u, 0)` and `S(u, 1)`, and `v_curves`, `S(0, v)`
+    /// and `S(1, v)`. Both domains are `[0, 1]`.
+    pub fn try_coons(
+        &self,
+        u_curves: [&BSpline; 2],
+        v_curves: [&BSpline; 2],
+    ) -> Result<BSplineSurface, BSplineSurfaceCoonsError> {
+        let mut u = u_curves.map(|c| c.clone());
+        let mut v = v_curves.map(|c| c.clone());
+        BSpline::make_compatible(&mut u)?;
+        BSpline::make_compatible(&mut v)?;
+        let (first, last) = (0, u[0].poles.len() - 1);
+        let corners = [
+            (u[0].poles[first].pole, v[0].poles[0].pole),
+            (u[0].poles[last].pole, v[1].poles[0].pole),
+            (
+                u[1].poles[first].pole,
+                v[0].poles[v[0].poles.len() - 1].pole,
+            ),
+            (u[1].poles[last].pole, v[1].poles[v[1].poles.len() - 1].pole),
+        ];
+        for (corner, (a, b)) in corners.into_iter().enumerate() {
+            let distance = a.distance(b);
+            if distance.is_nan() || distance > self.tolerance {
+                return Err(BSplineSurfaceCoonsError::NotConnected { corner, distance });
+            }
+        }
+
+        let degree = match self.blending {
+            BSplineCoonsBlending::Bilinear => 1,
+            BSplineCoonsBlending::Bicubic => 3,
+        };
+        // Bezier poles of the blending functions, `side(k)` the boundary of pole `k`.
+        let side = |k: usize| 2 * k / (degree + 1);
+        let blend = |a: &BSpline, b: &BSpline| {
+            let poles = a
+                .poles
+                .iter()
+                .zip(&b.poles)
+                .map(|(&pa, &pb)| (0..=degree).map(|k| [pa, pb][side(k)]).collect())
+                .collect();
+            BSplineSurface::try_new(
+                a.degree,
+                degree,
+                a.knots.clone(),
+                bezier_knots(degree),
+                poles,
+                a.use_rational,
+            )
+        };
+        let along_u = blend(&u[0], &u[1])?;
+        let along_v = blend(&v[0], &v[1])?.transposed();
+        let ends = [
+            [u[0].poles[first], u[1].poles[first]],
+            [u[0].poles[last], u[1].poles[last]],
+        ];
+        let poles = (0..=degree)
+            .map(|i| (0..=degree).map(|j| ends[side(i)][side(j)]).collect())
+            .collect();
+        let corners = BSplineSurface::try_new(
+            degree,
+            degree,
+            bezier_knots(degree),
+            bezier_knots(degree),
+            poles,
+            u[0].use_rational,
+        )?;
+        Ok(boolean_sum([along_u, along_v, corners])?)
+    }
+}
+
+/// Clamped knots of a single Bezier segment on `[0, 1]`.
+pub(crate) fn bezier_knots(degree: usize) -> BSplineKnots {
+    let flatten = [vec![0.; degree + 1], vec![1.; degree + 1]].concat();
+    BSplineKnots::try_from_flatten(&flatten, degree, degree + 1, false).expect("Bezier knots.")
+}
+
+/// `first + second - third`, made compatible and summed on homogeneous poles.
+pub(crate) fn boolean_sum(
+    mut surfaces: [BSplineSurface; 3],
+) -> Result<BSplineSurface, BSplineSurfaceError> {
+    BSplineSurface::make_compatible(&mut surfaces)?;
+    let [first, second, third] = &surfaces;
+    let poles = (0..first.nb_poles_u)
+        .map(|i| {
+            (0..first.nb_poles_v)
+                .map(|j| {
+                    let homogeneous = |s: &BSplineSurface| {
+                        let p = s.weighted_pole(i, j);
+                        p.pole.extend(p.weight)
+                    };
+                    let pw = homogeneous(first) + homogeneous(second) - homogeneous(third);
+                    BSplinePole {
+                        pole: pw.truncate() / pw.w,
+                        weight: pw.w,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    BSplineSurface::try_new(
+        first.degree_u,
+        first.degree_v,
+        first.knots_u.clone(),
+        first.knots_v.clone(),
+        poles,
+        first.use_rational,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::Vec3;
+
+    fn curve(degree: usize, poles: &[Vec3], weights: &[Real]) -> BSpline {
+        let knots = [vec![0.; degree + 1], vec![2.; degree + 1]].concat();
+        let knots = BSplineKnots::try_from_flatten(&knots, degree, poles.len(), false).unwrap();
+        let poles = poles
+            .iter()
+            .zip(weights)
+            .map(|(&pole, &weight)| BSplinePole { pole, weight })
+            .collect();
+        BSpline::try_new(degree, knots, poles, true).unwrap()
+    }
+
+    #[test]
+    fn coons_reproduces_boundaries() {
+        let (a, b) = (Vec3::ZERO, Vec3::new(2., 0., 0.5));
+        let (c, d) = (Vec3::new(0., 2., -0.5), Vec3::new(2., 2., 0.));
+        let u_curves = [
+            curve(2, &[a, Vec3::new(1., -0.5, 1.), b], &[1., 2., 1.]),
+            curve(
+                3,
+                &[c, Vec3::new(0.7, 2.3, 0.), Vec3::new(1.4, 1.8, 1.), d],
+                &[1.; 4],
+            ),
+        ];
+        let v_curves = [
+            curve(2, &[a, Vec3::new(-0.5, 1., 0.), c], &[1.; 3]),
+            curve(2, &[b, Vec3::new(2.5, 1., 1.), d], &[1., 0.5, 1.]),
+        ];
+        for blending in [
+            BSplineCoonsBlending::Bilinear,
+            BSplineCoonsBlending::Bicubic,
+        ] {
+            let coons = BSplineSurfaceCoons {
+                blending,
+                tolerance: 1e-5,
+            }
+            .try_coons([&u_curves[0], &u_curves[1]], [&v_curves[0], &v_curves[1]])
+            .unwrap();
+            for i in 0..=10 {
+                let t = i as Real / 10.;
+                for (k, side) in [0., 1.].into_iter().enumerate() {
+                    let along_u = u_curves[k].interop(2. * t);
+                    let along_v = v_curves[k].interop(2. * t);
+                    assert!(coons.interop(t, side).distance(along_u) < 1e-4);
+                    assert!(coons.interop(side, t).distance(along_v) < 1e-4);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::precision::{Real, Vec3};
+
+use super::BSplineSurface;
+
+/// First `(e, f, g)` and second `(l, m, n)` fundamental forms at a point.
+///
+/// The second form is measured along the normal `S_u x S_v`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BSplineSurfaceFundamentalForms {
+    pub e: Real,
+    pub f: Real,
+    pub g: Real,
+    pub l: Real,
+    pub m: Real,
+    pub n: Real,
+}
+
+/// Curvatures at a point, signed along the normal `S_u x S_v`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BSplineSurfaceCurvature {
+    pub gaussian: Real,
+    pub mean: Real,
+    /// Principal curvatures, the largest first.
+    pub principal: [Real; 2],
+    /// Unit tangent directions of the principal curvatures.
+    pub directions: [Vec3; 2],
+}
+
+impl BSplineSurface {
+    /// Unit normal `S_u x S_v`, `None` where the surface is degenerate.
+    pub fn normal(&self, u: Real, v: Real) -> Option<Vec3> {
+        let ders = self.derivatives(u, v, 1);
+        ders[1][0].cross(ders[0][1]).try_normalize()
+    }
+
+    /// Fundamental forms, `None` where the surface is degenerate.
+    pub fn fundamental_forms(&self, u: Real, v: Real) -> Option<BSplineSurfaceFundamentalForms> {
+        let ders = self.derivatives(u, v, 2);
+        Self::forms_from_derivatives(&ders)
+    }
+
+    /// Gaussian, mean and principal curvatures, `None` where the surface is degenerate.
+    pub fn curvature(&self, u: Real, v: Real) -> Option<BSplineSurfaceCurvature> {
+        let ders = self.derivatives(u, v, 2);
+        let forms = Self::forms_from_derivatives(&ders)?;
+        let BSplineSurfaceFundamentalForms { e, f, g, l, m, n } = forms;
+        let det = e * g - f * f;
+        let gaussian = (l * n - m * m) / det;
+        let mean = (e * n + g * l - 2. * f * m) / (2. * det);
+        let root = (mean * mean - gaussian).max(0.).sqrt();
+        let principal = [mean + root, mean - root];
+
+        let (su, sv) = (ders[1][0], ders[0][1]);
+        // Tangent `du S_u + dv S_v` in the kernel of `II - k I`.
+        let direction = |k: Real| {
+            let (a, b, c) = (l - k * e, m - k * f, n - k * g);
+            let (du, dv) = if a.abs() + b.abs() >= b.abs() + c.abs() {
+                (b, -a)
+            } else {
+                (c, -b)
+            };
+            (du * su + dv * sv).try_normalize()
+        };
+        let first = direction(principal[0]).unwrap_or_else(|| su.normalize());
+        let normal = su.cross(sv).normalize();
+        // Principal directions are orthogonal, which also covers umbilics.
+        let second = normal.cross(first);
+        Some(BSplineSurfaceCurvature {
+            gaussian,
+            mean,
+            principal,
+            directions: [first, second],
+        })
+    }
+
+    fn forms_from_derivatives(ders: &[Vec<Vec3>]) -> Option<BSplineSurfaceFundamentalForms> {
+        let (su, sv) = (ders[1][0], ders[0][1]);
+        let normal = su.cross(sv).try_normalize()?;
+        Some(BSplineSurfaceFundamentalForms {
+            e: su.dot(su),
+            f: su.dot(sv),
+            g: sv.dot(sv),
+            l: ders[2][0].dot(normal),
+            m: ders[1][1].dot(normal),
+            n: ders[0][2].dot(normal),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{bspline_knots::BSplineKnots, bspline_poles::BSplinePole, BSpline};
+    use crate::precision::consts;
+
+    const RADIUS: Real = 2.;
+
+    /// Rational circle arcs of `RADIUS` in the `(x, z)` plane through the corners
+    /// `(x, z)`, a quarter turn each.
+    fn arcs(corners: &[(Real, Real)]) -> BSpline {
+        let nb_arcs = (corners.len() - 1) / 2;
+        let mut flatten = vec![0.; 3];
+        for i in 1..nb_arcs {
+            let knot = i as Real / nb_arcs as Real;
+            flatten.extend([knot, knot]);
+        }
+        flatten.extend([1.; 3]);
+        let knots = BSplineKnots::try_from_flatten(&flatten, 2, corners.len(), false).unwrap();
+        let poles = corners
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, z))| BSplinePole {
+                pole: RADIUS * Vec3::new(x, 0., z),
+                weight: if i % 2 == 1 { Real::sqrt(0.5) } else { 1. },
+            })
+            .collect();
+        BSpline::try_new(2, knots, poles, true).unwrap()
+    }
+
+    fn samples() -> impl Iterator<Item = (Real, Real)> {
+        (1..10).flat_map(|i| (0..=10).map(move |j| (i as Real / 10., j as Real / 10.)))
+    }
+
+    #[test]
+    fn sphere_curvature() {
+        let meridian = arcs(&[(0., -1.), (1., -1.), (1., 0.), (1., 1.), (0., 1.)]);
+        let sphere =
+            BSplineSurface::try_revolution(&meridian, Vec3::ZERO, Vec3::Z, consts::TAU).unwrap();
+        for (u, v) in samples() {
+            let curvature = sphere.curvature(u, v).unwrap();
+            // The normal points inwards or outwards, the sign of `H` follows it.
+            let outwards = sphere.normal(u, v).unwrap().dot(sphere.interop(u, v)) > 0.;
+            let k = if outwards { -1. } else { 1. } / RADIUS;
+            assert!((curvature.gaussian - 1. / (RADIUS * RADIUS)).abs() < 1e-4);
+            assert!((curvature.mean - k).abs() < 1e-4);
+            // Umbilic, `sqrt(H^2 - K)` magnifies the rounding of `H` and `K`.
+            for principal in curvature.principal {
+                assert!((principal - k).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    fn cylinder_curvature() {
+        let circle = arcs(&[
+            (1., 0.),
+            (1., 1.),
+            (0., 1.),
+            (-1., 1.),
+            (-1., 0.),
+            (-1., -1.),
+            (0., -1.),
+            (1., -1.),
+            (1., 0.),
+        ]);
+        let cylinder = BSplineSurface::try_extrusion(&circle, Vec3::new(0., 3., 0.)).unwrap();
+        for (u, v) in samples() {
+            let curvature = cylinder.curvature(u, v).unwrap();
+            assert!(curvature.gaussian.abs() < 1e-4);
+            assert!((curvature.mean.abs() - 0.5 / RADIUS).abs() < 1e-4);
+            let [bent, straight] = if curvature.principal[0].abs() > curvature.principal[1].abs() {
+                [0, 1]
+            } else {
+                [1, 0]
+            };
+            assert!((curvature.principal[bent].abs() - 1. / RADIUS).abs() < 1e-4);
+            assert!(curvature.principal[straight].abs() < 1e-4);
+            // Around the circle and along the axis.
+            assert!(curvature.directions[bent].y.abs() < 1e-4);
+            assert!((curvature.directions[straight].y.abs() - 1.).abs() < 1e-4);
+        }
+    }
+}
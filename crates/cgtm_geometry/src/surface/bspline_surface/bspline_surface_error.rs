@@ -0,0 +1,167 @@
+use thiserror::Error;
+
+use crate::{
+    curve::bspline::bspline_error::{
+        BSplineApproximationError, BSplineError, BSplineInterpolationError, BSplineKnotsGenError,
+    },
+    precision::Real,
+};
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceError {
+    #[error("In u direction: {0}")]
+    U(BSplineError),
+    #[error("In v direction: {0}")]
+    V(BSplineError),
+    #[error("Row {row} has {actual} poles, expected {expected}.")]
+    NotRectangular {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Pole ({i}, {j}) has a non-finite coordinate.")]
+    NonFinitePole { i: usize, j: usize },
+    #[error("Pole ({i}, {j}) has weight {weight}, weights should be finite and positive.")]
+    NonPositiveWeight { i: usize, j: usize, weight: Real },
+    #[error(
+        "Pole ({i}, {j}) is out of range, the surface has {nb_poles_u} by {nb_poles_v} poles."
+    )]
+    PoleIndexOutOfRange {
+        i: usize,
+        j: usize,
+        nb_poles_u: usize,
+        nb_poles_v: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceApproximationError {
+    #[error("In u direction: {0}")]
+    U(BSplineApproximationError),
+    #[error("In v direction: {0}")]
+    V(BSplineApproximationError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+    #[error("Row {row} has {actual} points, expected {expected}.")]
+    NotRectangular {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Grid has no point.")]
+    EmptyGrid,
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceConstructionError {
+    #[error(transparent)]
+    Knots(#[from] BSplineError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+    #[error("Extrusion direction should be finite and non-zero.")]
+    ZeroDirection,
+    #[error("Revolution axis should be finite and non-zero.")]
+    ZeroAxis,
+    #[error("Revolution angle {angle} should be in (0, 2 pi].")]
+    AngleOutOfRange { angle: Real },
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceInterpolationError {
+    #[error("In u direction: {0}")]
+    U(BSplineInterpolationError),
+    #[error("In v direction: {0}")]
+    V(BSplineInterpolationError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+    #[error("Row {row} has {actual} points, expected {expected}.")]
+    NotRectangular {
+        row: usize,
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Grid has no point.")]
+    EmptyGrid,
+    #[error("Boundary derivatives should be {expected}, got {actual}.")]
+    DerivativesNumberNotMatch { expected: usize, actual: usize },
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceLoftError {
+    #[error("Loft needs at least 2 sections, got {0}.")]
+    TooFewSections(usize),
+    #[error("Tangents need an open loft interpolating the sections.")]
+    TangentsNotSupported,
+    #[error("Closed lofts interpolate the sections, approximation is not supported.")]
+    ClosedApproximation,
+    #[error(transparent)]
+    Knots(#[from] BSplineError),
+    #[error(transparent)]
+    Interpolation(#[from] BSplineInterpolationError),
+    #[error(transparent)]
+    Approximation(#[from] BSplineApproximationError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceSweepError {
+    #[error(transparent)]
+    BSplineErr(#[from] BSplineError),
+    #[error(transparent)]
+    Loft(#[from] BSplineSurfaceLoftError),
+    #[error("Path has no tangent or rails meet.")]
+    DegeneratePath,
+    #[error("Profile start and end should differ to sweep along two rails.")]
+    DegenerateProfile,
+    #[error("Sweep deviates by {deviation} with the most sections allowed.")]
+    ToleranceNotReached { deviation: Real },
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceCoonsError {
+    #[error("Curves are {distance} apart at corner {corner}.")]
+    NotConnected { corner: usize, distance: Real },
+    #[error(transparent)]
+    Knots(#[from] BSplineError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceGordonError {
+    #[error("Network needs at least 2 curves in each direction, got {u} and {v}.")]
+    TooFewCurves { u: usize, v: usize },
+    #[error(
+        "U-curve {u_curve} and v-curve {v_curve} are {distance} apart where they should meet."
+    )]
+    NetworkNotCompatible {
+        u_curve: usize,
+        v_curve: usize,
+        distance: Real,
+    },
+    #[error("First or last u-curve is {distance} away from the ends of the v-curves.")]
+    UBoundaryNotReached { distance: Real },
+    #[error("First or last v-curve is {distance} away from the ends of the u-curves.")]
+    VBoundaryNotReached { distance: Real },
+    #[error(transparent)]
+    Knots(#[from] BSplineError),
+    #[error(transparent)]
+    Interpolation(#[from] BSplineInterpolationError),
+    #[error(transparent)]
+    Loft(#[from] BSplineSurfaceLoftError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+}
+
+#[derive(Debug, Clone, Copy, Error)]
+pub enum BSplineSurfaceFittingError {
+    #[error(transparent)]
+    KnotsGeneration(#[from] BSplineKnotsGenError),
+    #[error(transparent)]
+    Surface(#[from] BSplineSurfaceError),
+    #[error("Points should be at least 3 and not all on a line.")]
+    DegeneratePointCloud,
+    #[error("Normal equations are singular, increase the smoothing.")]
+    SingularNormalEquations,
+}
@@ -0,0 +1,404 @@
+use f3l::{compute_covariance_matrix, jacobi_eigen_square_n, BasicFloat};
+
+use crate::curve::bspline::{
+    bspline_domain::BSplineDomain,
+    bspline_knots::{BSplineFeature, BSplineKnots, BSplineKnotsAlgo},
+    bspline_poles::BSplinePole,
+    utils::{basis_functions, find_span, solve_banded_cholesky},
+};
+use crate::precision::{Real, Vec3};
+
+use super::{bspline_surface_error::BSplineSurfaceFittingError, BSplineSurface};
+
+/// Samples per direction to find the starting point of a projection on a base surface.
+const PROJECTION_SAMPLES: usize = 24;
+/// Newton iterations when projecting a point on a surface.
+const PROJECTION_ITERATIONS: usize = 16;
+
+/// Least-squares fit of a clamped surface to an unorganized point cloud.
+///
+/// Points get parameters by projection on a base surface, the best-fit plane by
+/// default. The bending energy of the pole grid is added to the squared distances, so
+/// regions without points stay smooth. Parameters are then corrected by projecting the
+/// points on the fitted surface and fitting again.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceFitting {
+    pub degree_u: usize,
+    pub degree_v: usize,
+    pub nb_poles_u: usize,
+    pub nb_poles_v: usize,
+    /// Weight of the bending energy, relative to the squared distances per pole.
+    pub smoothing: Real,
+    /// Number of parameter corrections.
+    pub iterations: usize,
+}
+
+impl Default for BSplineSurfaceFitting {
+    fn default() -> Self {
+        Self {
+            degree_u: 3,
+            degree_v: 3,
+            nb_poles_u: 8,
+            nb_poles_v: 8,
+            smoothing: 1e-3,
+            iterations: 3,
+        }
+    }
+}
+
+impl BSplineSurfaceFitting {
+    /// Fits `points`, parameterized on their best-fit plane.
+    pub fn try_fit<Q, T>(&self, points: &[Q]) -> Result<BSplineSurface, BSplineSurfaceFittingError>
+    where
+        Q: Into<[T; 3]> + Copy,
+        T: BasicFloat,
+    {
+        let points = to_points(points);
+        let params = plane_parameters(&points)?;
+        self.fit_and_correct(&points, params)
+    }
+
+    /// Fits `points`, parameterized by projection on `base`.
+    pub fn try_fit_with_base<Q, T>(
+        &self,
+        points: &[Q],
+        base: &BSplineSurface,
+    ) -> Result<BSplineSurface, BSplineSurfaceFittingError>
+    where
+        Q: Into<[T; 3]> + Copy,
+        T: BasicFloat,
+    {
+        let points = to_points(points);
+        let (du, dv) = (base.domain_u(), base.domain_v());
+        let params = base
+            .project_all(&points)
+            .into_iter()
+            .map(|(u, v)| ((u - du.lower) / du.length(), (v - dv.lower) / dv.length()))
+            .collect();
+        self.fit_and_correct(&points, params)
+    }
+
+    fn fit_and_correct(
+        &self,
+        points: &[Vec3],
+        mut params: Vec<(Real, Real)>,
+    ) -> Result<BSplineSurface, BSplineSurfaceFittingError> {
+        let mut surface = self.fit(points, &params)?;
+        for _ in 0..self.iterations {
+            params = points
+                .iter()
+                .zip(&params)
+                .map(|(&p, &(u, v))| surface.closest_parameters(p, u, v))
+                .collect();
+            surface = self.fit(points, &params)?;
+        }
+        Ok(surface)
+    }
+
+    fn fit(
+        &self,
+        points: &[Vec3],
+        params: &[(Real, Real)],
+    ) -> Result<BSplineSurface, BSplineSurfaceFittingError> {
+        let (p, q) = (self.degree_u, self.degree_v);
+        let (nb_u, nb_v) = (self.nb_poles_u, self.nb_poles_v);
+        let knots_u = BSplineKnots::try_build(
+            p,
+            &BSplineKnotsAlgo::Uniform(nb_u),
+            BSplineFeature::ClampAll,
+        )?;
+        let knots_v = BSplineKnots::try_build(
+            q,
+            &BSplineKnotsAlgo::Uniform(nb_v),
+            BSplineFeature::ClampAll,
+        )?;
+        let (flat_u, flat_v) = (knots_u.original_flatten(), knots_v.original_flatten());
+
+        // Unknowns are the poles in row-major order, a span couples indices at most
+        // `p * nb_v + q` apart, and the bending energy `nb_v + 1` apart.
+        let m = nb_u * nb_v;
+        let bandwidth = (p * nb_v + q).max(nb_v + 1);
+        let w = bandwidth + 1;
+        let mut band = vec![0.; m * w];
+        let mut rhs = vec![Vec3::ZERO; m];
+        let mut add = |entries: &[(usize, Real)], weight: Real| {
+            for &(i, a) in entries {
+                for &(j, b) in entries {
+                    if j <= i {
+                        band[i * w + i - j] += weight * a * b;
+                    }
+                }
+            }
+        };
+
+        let mut entries = Vec::with_capacity((p + 1) * (q + 1));
+        for (&point, &(u, v)) in points.iter().zip(params) {
+            let span_u = find_span(u, &flat_u, p, nb_u);
+            let span_v = find_span(v, &flat_v, q, nb_v);
+            let nu = basis_functions(span_u, u, &flat_u, p);
+            let nv = basis_functions(span_v, v, &flat_v, q);
+            entries.clear();
+            for (a, &na) in nu.iter().enumerate() {
+                for (b, &nb) in nv.iter().enumerate() {
+                    let index = (span_u - p + a) * nb_v + span_v - q + b;
+                    entries.push((index, na * nb));
+                    rhs[index] += na * nb * point;
+                }
+            }
+            add(&entries, 1.);
+        }
+
+        // Bending energy `|S_uu|^2 + 2 |S_uv|^2 + |S_vv|^2` on pole differences.
+        let lambda = self.smoothing * points.len() as Real / m as Real;
+        let index = |i: usize, j: usize| i * nb_v + j;
+        for i in 0..nb_u {
+            for j in 0..nb_v {
+                if i + 2 < nb_u {
+                    let entries = [
+                        (index(i, j), 1.),
+                        (index(i + 1, j), -2.),
+                        (index(i + 2, j), 1.),
+                    ];
+                    add(&entries, lambda);
+                }
+                if j + 2 < nb_v {
+                    let entries = [
+                        (index(i, j), 1.),
+                        (index(i, j + 1), -2.),
+                        (index(i, j + 2), 1.),
+                    ];
+                    add(&entries, lambda);
+                }
+                if i + 1 < nb_u && j + 1 < nb_v {
+                    let entries = [
+                        (index(i, j), 1.),
+                        (index(i + 1, j), -1.),
+                        (index(i, j + 1), -1.),
+                        (index(i + 1, j + 1), 1.),
+                    ];
+                    add(&entries, 2. * lambda);
+                }
+            }
+        }
+
+        let poles = solve_banded_cholesky(band, bandwidth, rhs)
+            .ok_or(BSplineSurfaceFittingError::SingularNormalEquations)?;
+        let poles = poles
+            .chunks(nb_v)
+            .map(|row| {
+                row.iter()
+                    .map(|&pole| BSplinePole { pole, weight: 1. })
+                    .collect()
+            })
+            .collect();
+        Ok(BSplineSurface::try_new(
+            p, q, knots_u, knots_v, poles, false,
+        )?)
+    }
+}
+
+impl BSplineSurface {
+    /// Parameters of the point of the surface closest to `point`, by Newton iterations
+    /// from `(u, v)`. Parameters stay in the domain, periodic directions wrap.
+    pub fn closest_parameters(&self, point: Vec3, u: Real, v: Real) -> (Real, Real) {
+        let (du, dv) = (self.domain_u(), self.domain_v());
+        let fit = |t: Real, domain: BSplineDomain, periodic: bool| {
+            if periodic {
+                domain.wrap(t)
+            } else {
+                domain.clamp(t)
+            }
+        };
+        let (mut u, mut v) = (u, v);
+        for _ in 0..PROJECTION_ITERATIONS {
+            let ders = self.derivatives(u, v, 2);
+            let r = ders[0][0] - point;
+            let (su, sv) = (ders[1][0], ders[0][1]);
+            let f = r.dot(su);
+            let g = r.dot(sv);
+            let a = su.dot(su) + r.dot(ders[2][0]);
+            let b = su.dot(sv) + r.dot(ders[1][1]);
+            let c = sv.dot(sv) + r.dot(ders[0][2]);
+            let det = a * c - b * b;
+            if det.abs() <= Real::EPSILON {
+                break;
+            }
+            let delta_u = (c * f - b * g) / det;
+            let delta_v = (a * g - b * f) / det;
+            let (nu, nv) = (
+                fit(u - delta_u, du, self.is_periodic_u()),
+                fit(v - delta_v, dv, self.is_periodic_v()),
+            );
+            let step = (nu - u).abs() / du.length() + (nv - v).abs() / dv.length();
+            (u, v) = (nu, nv);
+            if step <= Real::EPSILON {
+                break;
+            }
+        }
+        (u, v)
+    }
+
+    /// Projects each point, starting from the closest of a grid of samples.
+    fn project_all(&self, points: &[Vec3]) -> Vec<(Real, Real)> {
+        let (du, dv) = (self.domain_u(), self.domain_v());
+        let step = |i: usize, d: &BSplineDomain| {
+            d.lower + d.length() * i as Real / (PROJECTION_SAMPLES - 1) as Real
+        };
+        let samples = (0..PROJECTION_SAMPLES)
+            .flat_map(|i| (0..PROJECTION_SAMPLES).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let (u, v) = (step(i, &du), step(j, &dv));
+                (self.interop(u, v), u, v)
+            })
+            .collect::<Vec<_>>();
+        points
+            .iter()
+            .map(|&p| {
+                let &(_, u, v) = samples
+                    .iter()
+                    .min_by(|a, b| a.0.distance_squared(p).total_cmp(&b.0.distance_squared(p)))
+                    .unwrap();
+                self.closest_parameters(p, u, v)
+            })
+            .collect()
+    }
+}
+
+fn to_points<Q, T>(points: &[Q]) -> Vec<Vec3>
+where
+    Q: Into<[T; 3]> + Copy,
+    T: BasicFloat,
+{
+    points
+        .iter()
+        .map(|&q| {
+            let [x, y, z] = q.into();
+            let cast = |t: T| t.to_f64().unwrap() as Real;
+            Vec3::new(cast(x), cast(y), cast(z))
+        })
+        .collect()
+}
+
+/// Coordinates on the two main axes of the points, normalized to `[0, 1]`.
+fn plane_parameters(points: &[Vec3]) -> Result<Vec<(Real, Real)>, BSplineSurfaceFittingError> {
+    if points.len() < 3 {
+        return Err(BSplineSurfaceFittingError::DegeneratePointCloud);
+    }
+    let arrays = points.iter().map(|p| p.to_array()).collect::<Vec<_>>();
+    let (covariance, mean) = compute_covariance_matrix::<_, Real, 3>(&arrays);
+    let mut eigens = jacobi_eigen_square_n(covariance);
+    eigens.sort_by(|a, b| b.eigenvalue.total_cmp(&a.eigenvalue));
+    if eigens[1].eigenvalue <= Real::EPSILON * eigens[0].eigenvalue.max(Real::MIN_POSITIVE) {
+        return Err(BSplineSurfaceFittingError::DegeneratePointCloud);
+    }
+    let (axis_u, axis_v) = (
+        Vec3::from_array(eigens[0].eigenvector),
+        Vec3::from_array(eigens[1].eigenvector),
+    );
+    let mean = Vec3::from_array(mean);
+    let coords = points
+        .iter()
+        .map(|&p| ((p - mean).dot(axis_u), (p - mean).dot(axis_v)))
+        .collect::<Vec<_>>();
+    let bounds = |f: fn(&(Real, Real)) -> Real| {
+        coords
+            .iter()
+            .map(f)
+            .fold((Real::MAX, Real::MIN), |(lo, hi), c| (lo.min(c), hi.max(c)))
+    };
+    let (lo_u, hi_u) = bounds(|c| c.0);
+    let (lo_v, hi_v) = bounds(|c| c.1);
+    Ok(coords
+        .into_iter()
+        .map(|(a, b)| ((a - lo_u) / (hi_u - lo_u), (b - lo_v) / (hi_v - lo_v)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOISE: Real = 1e-3;
+
+    fn quadric(x: Real, y: Real) -> Real {
+        0.3 * (x * x - y * y) + 0.2 * x * y
+    }
+
+    /// Points of the quadric over `[-1, 1]^2`, jittered along x and y, with noise
+    /// along z.
+    fn cloud() -> Vec<Vec3> {
+        let n = 20;
+        (0..n * n)
+            .map(|k| {
+                let (i, j) = ((k / n) as Real, (k % n) as Real);
+                let jitter = |a: Real| 0.3 * (a * 12.9898).sin() / n as Real;
+                let x = -1. + 2. * (i + 0.5) / n as Real + jitter(j + 0.7 * i);
+                let y = -1. + 2. * (j + 0.5) / n as Real + jitter(i + 1.3 * j);
+                let noise = NOISE * (k as Real * 78.233).sin();
+                Vec3::new(x, y, quadric(x, y) + noise)
+            })
+            .collect()
+    }
+
+    /// Largest distance along z between the quadric and the surface inside the cloud.
+    fn deviation(surface: &BSplineSurface) -> Real {
+        (0..=20)
+            .flat_map(|i| (0..=20).map(move |j| (i as Real / 20., j as Real / 20.)))
+            .map(|(u, v)| surface.interop(u, v))
+            .filter(|p| p.x.abs() <= 0.9 && p.y.abs() <= 0.9)
+            .map(|p| (p.z - quadric(p.x, p.y)).abs())
+            .fold(0., Real::max)
+    }
+
+    #[test]
+    fn fit_noisy_quadric() {
+        let fitting = BSplineSurfaceFitting::default();
+        let surface = fitting.try_fit(&cloud()).unwrap();
+        assert!(deviation(&surface) < 3. * NOISE);
+    }
+
+    #[test]
+    fn fit_noisy_quadric_with_base() {
+        let knots = || BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).unwrap();
+        let poles = [-1.2, 1.2]
+            .map(|x| {
+                [-1.2, 1.2]
+                    .map(|y| BSplinePole {
+                        pole: Vec3::new(x, y, 0.),
+                        weight: 1.,
+                    })
+                    .to_vec()
+            })
+            .to_vec();
+        let base = BSplineSurface::try_new(1, 1, knots(), knots(), poles, false).unwrap();
+        let fitting = BSplineSurfaceFitting::default();
+        let surface = fitting.try_fit_with_base(&cloud(), &base).unwrap();
+        assert!(deviation(&surface) < 3. * NOISE);
+    }
+
+    #[test]
+    fn closest_parameters_project_along_normal() {
+        let surface = BSplineSurfaceFitting::default().try_fit(&cloud()).unwrap();
+        for (u, v) in [(0.3, 0.6), (0.5, 0.5), (0.8, 0.2)] {
+            let point = surface.interop(u, v) + 0.05 * surface.normal(u, v).unwrap();
+            let (pu, pv) = surface.closest_parameters(point, u + 0.1, v - 0.1);
+            assert!((pu - u).abs() < 1e-3 && (pv - v).abs() < 1e-3);
+        }
+        // Beyond the boundary, the parameter stays on it.
+        let outside = surface.interop(1., 0.5) + 0.5 * surface.derivatives(1., 0.5, 1)[1][0];
+        let (pu, _) = surface.closest_parameters(outside, 0.9, 0.5);
+        assert_eq!(pu, 1.);
+    }
+
+    #[test]
+    fn degenerate_point_cloud() {
+        let fitting = BSplineSurfaceFitting::default();
+        let line = (0..10).map(|i| Vec3::splat(i as Real)).collect::<Vec<_>>();
+        for points in [&line[..2], &line[..]] {
+            assert!(matches!(
+                fitting.try_fit(points),
+                Err(BSplineSurfaceFittingError::DegeneratePointCloud)
+            ));
+        }
+    }
+}
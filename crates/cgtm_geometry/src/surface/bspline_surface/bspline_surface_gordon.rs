@@ -0,0 +1,282 @@
+use crate::curve::bspline::{
+    bspline_data_knots::BSplineDataKnotsAlgo,
+    bspline_interpolation::InterpolationSystem,
+    bspline_poles::BSplinePole,
+    utils::{closest_on_curves, closest_on_segments},
+    BSpline,
+};
+use crate::precision::{Real, Vec4};
+
+use super::{
+    bspline_surface_coons::boolean_sum, bspline_surface_error::BSplineSurfaceGordonError,
+    bspline_surface_loft::BSplineSurfaceLoft, BSplineSurface,
+};
+
+/// Polyline samples per pole when searching where two curves meet.
+const MEETING_SAMPLES: usize = 8;
+/// Gauss-Newton iterations refining where two curves meet.
+const MEETING_ITERATIONS: usize = 32;
+
+/// Surface through a network of curves, The NURBS Book 10.5.
+///
+/// The surface is the boolean sum of the lofts across each family of curves, minus the
+/// interpolation of their intersections, computed on homogeneous poles like
+/// [`super::bspline_surface_coons::BSplineSurfaceCoons`].
+#[derive(Debug, Clone, Copy)]
+pub struct BSplineSurfaceGordon {
+    /// Degree along u, across the v-curves, lowered to their number minus one.
+    pub degree_u: usize,
+    /// Degree along v, across the u-curves, lowered to their number minus one.
+    pub degree_v: usize,
+    /// Largest gap allowed between curves where they meet.
+    pub tolerance: Real,
+}
+
+impl BSplineSurfaceGordon {
+    /// Surface with `u_curves` as u iso-curves and `v_curves` as v iso-curves.
+    ///
+    /// Each family is made compatible, see [`BSpline::make_compatible`], and ordered by
+    /// where it meets the first curve of the other. Every u-curve should meet every
+    /// v-curve at the same parameters, with the first and last curves of each family at
+    /// the ends of the other, within `tolerance`. Both domains are `[0, 1]`.
+    pub fn try_gordon(
+        &self,
+        u_curves: &[BSpline],
+        v_curves: &[BSpline],
+    ) -> Result<BSplineSurface, BSplineSurfaceGordonError> {
+        if u_curves.len() < 2 || v_curves.len() < 2 {
+            return Err(BSplineSurfaceGordonError::TooFewCurves {
+                u: u_curves.len(),
+                v: v_curves.len(),
+            });
+        }
+        let mut u = u_curves.to_vec();
+        let mut v = v_curves.to_vec();
+        BSpline::make_compatible(&mut u)?;
+        BSpline::make_compatible(&mut v)?;
+
+        // Parameter of each curve along the other direction, where it meets the first
+        // curve of the other family.
+        let mut vs = u.iter().map(|c| meeting(&v[0], c)).collect::<Vec<_>>();
+        let mut us = v.iter().map(|c| meeting(&u[0], c)).collect::<Vec<_>>();
+        sort_by_parameter(&mut u, &mut vs);
+        sort_by_parameter(&mut v, &mut us);
+        let distance = boundary_gap(&v[0], &vs);
+        if distance.is_nan() || distance > self.tolerance {
+            return Err(BSplineSurfaceGordonError::UBoundaryNotReached { distance });
+        }
+        let distance = boundary_gap(&u[0], &us);
+        if distance.is_nan() || distance > self.tolerance {
+            return Err(BSplineSurfaceGordonError::VBoundaryNotReached { distance });
+        }
+        (vs[0], us[0]) = (0., 0.);
+        (*vs.last_mut().unwrap(), *us.last_mut().unwrap()) = (1., 1.);
+        for (k, curve_u) in u.iter().enumerate() {
+            for (l, curve_v) in v.iter().enumerate() {
+                let distance = curve_u.interop(us[l]).distance(curve_v.interop(vs[k]));
+                if distance.is_nan() || distance > self.tolerance {
+                    return Err(BSplineSurfaceGordonError::NetworkNotCompatible {
+                        u_curve: k,
+                        v_curve: l,
+                        distance,
+                    });
+                }
+            }
+        }
+
+        let degree_u = self.degree_u.min(v.len() - 1).max(1);
+        let degree_v = self.degree_v.min(u.len() - 1).max(1);
+        let loft = |degree| BSplineSurfaceLoft {
+            degree,
+            data_knots_algo: BSplineDataKnotsAlgo::Uniform,
+            nb_poles: None,
+            closed: false,
+            start_tangent: None,
+            end_tangent: None,
+        };
+        let along_u = loft(degree_v).try_loft_with_parameters(&u, &vs)?;
+        let along_v = loft(degree_u)
+            .try_loft_with_parameters(&v, &us)?
+            .transposed();
+
+        // Tensor interpolation of the intersections, along v then along u.
+        let system_u = InterpolationSystem::try_new(degree_u, &us, false, false)?;
+        let system_v = InterpolationSystem::try_new(degree_v, &vs, false, false)?;
+        let rows = us
+            .iter()
+            .map(|&s| {
+                let points = u.iter().map(|c| homogeneous(c, s)).collect::<Vec<_>>();
+                system_v.solve(&points, None, None)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let columns = (0..rows[0].len())
+            .map(|j| {
+                let column = rows.iter().map(|row| row[j]).collect::<Vec<_>>();
+                system_u.solve(&column, None, None)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let poles = (0..columns[0].len())
+            .map(|i| {
+                columns
+                    .iter()
+                    .map(|column| BSplinePole {
+                        pole: column[i].truncate() / column[i].w,
+                        weight: column[i].w,
+                    })
+                    .collect()
+            })
+            .collect();
+        let intersections = BSplineSurface::try_new(
+            degree_u,
+            degree_v,
+            system_u.knots,
+            system_v.knots,
+            poles,
+            u[0].use_rational,
+        )?;
+        Ok(boolean_sum([along_u, along_v, intersections])?)
+    }
+}
+
+/// `(w C(u), w)`, `w = 1` when not rational.
+fn homogeneous(curve: &BSpline, u: Real) -> Vec4 {
+    if !curve.use_rational {
+        return curve.interop(u).extend(1.);
+    }
+    let weights = BSpline {
+        degree: curve.degree,
+        knots: curve.knots.clone(),
+        poles: curve
+            .poles
+            .iter()
+            .map(|p| BSplinePole {
+                pole: p.weight,
+                weight: 1.,
+            })
+            .collect(),
+        is_periodic: curve.is_periodic,
+        use_rational: false,
+    };
+    let w = weights.interop(u);
+    (w * curve.interop(u)).extend(w)
+}
+
+/// Parameter on `curve` of the point closest to `other`, from the closest segments of
+/// their polylines refined by Gauss-Newton.
+fn meeting(curve: &BSpline, other: &BSpline) -> Real {
+    let samples = |c: &BSpline| {
+        let n = MEETING_SAMPLES * c.poles.len();
+        let domain = c.domain();
+        (0..=n)
+            .map(|i| {
+                let u = domain.lower + domain.length() * i as Real / n as Real;
+                (u, c.interop(u))
+            })
+            .collect::<Vec<_>>()
+    };
+    let (a, b) = (samples(curve), samples(other));
+    let mut best = (Real::MAX, 0., 0.);
+    for sa in a.windows(2) {
+        for sb in b.windows(2) {
+            let (s, t, distance) = closest_on_segments(sa[0].1, sa[1].1, sb[0].1, sb[1].1);
+            if distance < best.0 {
+                let s = sa[0].0 + s * (sa[1].0 - sa[0].0);
+                let t = sb[0].0 + t * (sb[1].0 - sb[0].0);
+                best = (distance, s, t);
+            }
+        }
+    }
+
+    let (_, s, t) = best;
+    let (da, db) = (curve.domain(), other.domain());
+    closest_on_curves(
+        |s| curve.derivatives(s, 1),
+        |t| other.derivatives(t, 1),
+        |s, t| (da.clamp(s), db.clamp(t)),
+        (s, t),
+        0.,
+        MEETING_ITERATIONS,
+    )
+    .0
+}
+
+/// Largest distance on `curve` between the first and last of `params` and its ends.
+fn boundary_gap(curve: &BSpline, params: &[Real]) -> Real {
+    let first = curve.interop(params[0]).distance(curve.interop(0.));
+    let last = curve
+        .interop(params[params.len() - 1])
+        .distance(curve.interop(1.));
+    first.max(last)
+}
+
+/// Sorts `curves` by their `params`, both in place.
+fn sort_by_parameter(curves: &mut Vec<BSpline>, params: &mut Vec<Real>) {
+    let mut pairs = curves.drain(..).zip(params.drain(..)).collect::<Vec<_>>();
+    pairs.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (*curves, *params) = pairs.into_iter().unzip();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::bspline_knots::BSplineKnots;
+    use crate::precision::Vec3;
+
+    fn surface() -> BSplineSurface {
+        let knots_u =
+            BSplineKnots::try_from_flatten(&[0., 0., 0., 0., 1., 1., 1., 1.], 3, 4, false);
+        let knots_v = BSplineKnots::try_from_flatten(&[0., 0., 0., 1., 1., 1.], 2, 3, false);
+        let poles = (0..4)
+            .map(|i| {
+                (0..3)
+                    .map(|j| {
+                        let z = ((i * 3 + j) as Real * 0.7).sin();
+                        BSplinePole {
+                            pole: Vec3::new(i as Real, j as Real, z),
+                            weight: 1.,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        BSplineSurface::try_new(3, 2, knots_u.unwrap(), knots_v.unwrap(), poles, false).unwrap()
+    }
+
+    fn gordon() -> BSplineSurfaceGordon {
+        BSplineSurfaceGordon {
+            degree_u: 3,
+            degree_v: 3,
+            tolerance: 1e-4,
+        }
+    }
+
+    #[test]
+    fn gordon_reproduces_network() {
+        let surface = surface();
+        let (us, vs) = ([0., 0.3, 0.6, 1.], [0., 0.5, 1.]);
+        let u_curves = vs.map(|v| surface.iso_curve_v(v));
+        let v_curves = us.map(|u| surface.iso_curve_u(u));
+        let gordon = gordon().try_gordon(&u_curves, &v_curves).unwrap();
+        for i in 0..=10 {
+            let t = i as Real / 10.;
+            for (curve, &v) in u_curves.iter().zip(&vs) {
+                assert!(gordon.interop(t, v).distance(curve.interop(t)) < 1e-4);
+            }
+            for (curve, &u) in v_curves.iter().zip(&us) {
+                assert!(gordon.interop(u, t).distance(curve.interop(t)) < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn gordon_boundary_not_reached() {
+        let surface = surface();
+        // The u-curves start at `v = 0.2`, away from the ends of the v-curves.
+        let u_curves = [0.2, 0.5, 1.].map(|v| surface.iso_curve_v(v));
+        let v_curves = [0., 0.5, 1.].map(|u| surface.iso_curve_u(u));
+        assert!(matches!(
+            gordon().try_gordon(&u_curves, &v_curves),
+            Err(BSplineSurfaceGordonError::UBoundaryNotReached { .. })
+        ));
+    }
+}
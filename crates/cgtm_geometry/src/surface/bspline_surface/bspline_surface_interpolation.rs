@@ -0,0 +1,261 @@
+use crate::curve::bspline::{
+    bspline_interpolation::{BSplineInterpolation, InterpolationSystem},
+    bspline_poles::{BSplinePole, ControlPoint},
+};
+use crate::precision::Real;
+
+use super::{
+    average_parameters, bspline_surface_error::BSplineSurfaceInterpolationError, BSplineSurface,
+};
+
+/// Global interpolation of a grid of points, one `BSplineInterpolation` per direction.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceInterpolation {
+    pub u: BSplineInterpolation,
+    pub v: BSplineInterpolation,
+}
+
+/// First derivatives across the boundary of a grid, each optional.
+///
+/// `u_start[j]` is `S_u` at point `(0, j)` and `u_end[j]` at `(last, j)`, one per point
+/// along v. `v_start[i]` is `S_v` at point `(i, 0)` and `v_end[i]` at `(i, last)`, one
+/// per point along u.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceEndDerivatives<P> {
+    pub u_start: Option<Vec<P>>,
+    pub u_end: Option<Vec<P>>,
+    pub v_start: Option<Vec<P>>,
+    pub v_end: Option<Vec<P>>,
+}
+
+impl<P> Default for BSplineSurfaceEndDerivatives<P> {
+    fn default() -> Self {
+        Self {
+            u_start: None,
+            u_end: None,
+            v_start: None,
+            v_end: None,
+        }
+    }
+}
+
+impl BSplineSurfaceInterpolation {
+    /// Surface through `data[i][j]`, the `i`-th point along u and the `j`-th along v.
+    pub fn try_interpolate<P: ControlPoint>(
+        &self,
+        data: &[Vec<P>],
+    ) -> Result<BSplineSurface<P>, BSplineSurfaceInterpolationError> {
+        self.try_interpolate_with_derivatives(data, &BSplineSurfaceEndDerivatives::default())
+    }
+
+    /// Surface through `data`, with the given derivatives across its boundary.
+    ///
+    /// Parameters of each direction are generated per row (or column) by its
+    /// `data_knots_algo` and averaged. Rows are interpolated along v first, then the
+    /// resulting poles along u, The NURBS Book 9.2.5. Where derivatives are given in both
+    /// directions, the twist `S_uv` at the corner is estimated from their differences.
+    pub fn try_interpolate_with_derivatives<P: ControlPoint>(
+        &self,
+        data: &[Vec<P>],
+        derivatives: &BSplineSurfaceEndDerivatives<P>,
+    ) -> Result<BSplineSurface<P>, BSplineSurfaceInterpolationError> {
+        let nb_v = data.first().map_or(0, |row| row.len());
+        if let Some((row, r)) = data.iter().enumerate().find(|(_, r)| r.len() != nb_v) {
+            return Err(BSplineSurfaceInterpolationError::NotRectangular {
+                row,
+                expected: nb_v,
+                actual: r.len(),
+            });
+        }
+        if data.is_empty() || nb_v == 0 {
+            return Err(BSplineSurfaceInterpolationError::EmptyGrid);
+        }
+        let nb_u = data.len();
+        for (ders, expected) in [
+            (&derivatives.u_start, nb_v),
+            (&derivatives.u_end, nb_v),
+            (&derivatives.v_start, nb_u),
+            (&derivatives.v_end, nb_u),
+        ] {
+            if let Some(ders) = ders.as_ref().filter(|d| d.len() != expected) {
+                return Err(
+                    BSplineSurfaceInterpolationError::DerivativesNumberNotMatch {
+                        expected,
+                        actual: ders.len(),
+                    },
+                );
+            }
+        }
+
+        let columns = (0..nb_v)
+            .map(|j| data.iter().map(|row| row[j]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let us = sequence_parameters(&self.u, &columns);
+        let vs = sequence_parameters(&self.v, data);
+        let BSplineSurfaceEndDerivatives {
+            u_start,
+            u_end,
+            v_start,
+            v_end,
+        } = derivatives;
+        let system_u =
+            InterpolationSystem::try_new(self.u.degree, &us, u_start.is_some(), u_end.is_some())
+                .map_err(BSplineSurfaceInterpolationError::U)?;
+        let system_v =
+            InterpolationSystem::try_new(self.v.degree, &vs, v_start.is_some(), v_end.is_some())
+                .map_err(BSplineSurfaceInterpolationError::V)?;
+
+        // `S_uv` at a corner, from the differences of both derivatives along its edges.
+        let twist = |ders_u: &Option<Vec<P>>, ders_v: &Option<Vec<P>>, i: usize, j: usize| {
+            let (ders_u, ders_v) = (ders_u.as_ref()?, ders_v.as_ref()?);
+            let (i0, i1) = if i == 0 { (0, 1) } else { (i - 1, i) };
+            let (j0, j1) = if j == 0 { (0, 1) } else { (j - 1, j) };
+            let along_v = (ders_u[j1] - ders_u[j0]) / (vs[j1] - vs[j0]);
+            let along_u = (ders_v[i1] - ders_v[i0]) / (us[i1] - us[i0]);
+            Some((along_v + along_u) * 0.5)
+        };
+        let (last_u, last_v) = (nb_u - 1, nb_v - 1);
+
+        // Along v: rows of points, and the u derivatives as rows of their own.
+        let along_v = |row: &[P], start: Option<P>, end: Option<P>| {
+            system_v
+                .solve(row, start, end)
+                .map_err(BSplineSurfaceInterpolationError::V)
+        };
+        let rows = data
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                along_v(
+                    row,
+                    v_start.as_ref().map(|d| d[i]),
+                    v_end.as_ref().map(|d| d[i]),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let row_u_start = u_start
+            .as_ref()
+            .map(|d| {
+                along_v(
+                    d,
+                    twist(u_start, v_start, 0, 0),
+                    twist(u_start, v_end, 0, last_v),
+                )
+            })
+            .transpose()?;
+        let row_u_end = u_end
+            .as_ref()
+            .map(|d| {
+                along_v(
+                    d,
+                    twist(u_end, v_start, last_u, 0),
+                    twist(u_end, v_end, last_u, last_v),
+                )
+            })
+            .transpose()?;
+
+        // Along u: each column of the poles found along v.
+        let nb_poles_v = rows[0].len();
+        let columns = (0..nb_poles_v)
+            .map(|l| {
+                let column = rows.iter().map(|row| row[l]).collect::<Vec<_>>();
+                system_u
+                    .solve(
+                        &column,
+                        row_u_start.as_ref().map(|r| r[l]),
+                        row_u_end.as_ref().map(|r| r[l]),
+                    )
+                    .map_err(BSplineSurfaceInterpolationError::U)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let nb_poles_u = columns[0].len();
+        let poles = (0..nb_poles_u)
+            .map(|k| {
+                columns
+                    .iter()
+                    .map(|column| BSplinePole {
+                        pole: column[k],
+                        weight: 1.,
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(BSplineSurface::try_new(
+            self.u.degree,
+            self.v.degree,
+            system_u.knots,
+            system_v.knots,
+            poles,
+            false,
+        )?)
+    }
+}
+
+/// Parameters in `[0, 1]` generated for each sequence of `data`, averaged, see
+/// [`average_parameters`].
+fn sequence_parameters<P: ControlPoint>(
+    interpolation: &BSplineInterpolation,
+    data: &[Vec<P>],
+) -> Vec<Real> {
+    let nb_params = data[0].len();
+    let uniform = (0..nb_params)
+        .map(|k| k as Real / (nb_params - 1).max(1) as Real)
+        .collect();
+    average_parameters(
+        data.iter()
+            .map(|sequence| interpolation.normalized_parameters(sequence)),
+        uniform,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::bspline_data_knots::BSplineDataKnotsAlgo;
+    use crate::precision::Vec3;
+
+    #[test]
+    fn interpolate_collapsed_row() {
+        // Cone, the first row collapses to the apex.
+        let data = (0..3)
+            .map(|i| {
+                (0..4)
+                    .map(|j| {
+                        let angle = (30. * j as Real).to_radians();
+                        let r = i as Real * (1. + 0.2 * j as Real);
+                        Vec3::new(r * angle.cos(), r * angle.sin(), i as Real)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let interpolation = |degree| BSplineInterpolation {
+            degree,
+            data_knots_algo: BSplineDataKnotsAlgo::ChordLength,
+        };
+        let surface = BSplineSurfaceInterpolation {
+            u: interpolation(2),
+            v: interpolation(3),
+        }
+        .try_interpolate(&data)
+        .unwrap();
+
+        let vs = sequence_parameters(&interpolation(3), &data);
+        let rows = data[1..]
+            .iter()
+            .map(|row| interpolation(3).normalized_parameters(row))
+            .collect::<Vec<_>>();
+        for (j, v) in vs.iter().enumerate() {
+            assert!((v - (rows[0][j] + rows[1][j]) / 2.).abs() < 1e-6);
+        }
+        let columns = (0..4)
+            .map(|j| data.iter().map(|row| row[j]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let us = sequence_parameters(&interpolation(2), &columns);
+        for (i, row) in data.iter().enumerate() {
+            for (j, point) in row.iter().enumerate() {
+                assert!(surface.interop(us[i], vs[j]).distance(*point) < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::curve::bspline::{
+    bspline_knots::BSplineKnots,
+    bspline_poles::{BSplinePole, ControlPoint},
+    utils::de_boor_homogeneous_in_place,
+    BSpline,
+};
+use crate::precision::Real;
+
+use super::{periodic_parameter, BSplineSurface};
+
+impl<P: ControlPoint> BSplineSurface<P> {
+    /// Curve `v -> S(u, v)` at constant `u`, exact, with the knots and degree of v.
+    ///
+    /// Each column of poles is reduced to one pole by de Boor along u on homogeneous
+    /// poles, so the curve is rational if the surface is.
+    pub fn iso_curve_u(&self, u: Real) -> BSpline<P> {
+        let poles = (0..self.nb_poles_v)
+            .map(|j| {
+                let column = (0..self.nb_poles_u)
+                    .map(|i| self.weighted_pole(i, j))
+                    .collect::<Vec<_>>();
+                iso_pole(u, &self.knots_u, self.degree_u, &column)
+            })
+            .collect();
+        self.iso_curve(self.degree_v, &self.knots_v, poles)
+    }
+
+    /// Curve `u -> S(u, v)` at constant `v`, exact, with the knots and degree of u.
+    ///
+    /// Same as [`BSplineSurface::iso_curve_u`] with u and v swapped.
+    pub fn iso_curve_v(&self, v: Real) -> BSpline<P> {
+        let poles = (0..self.nb_poles_u)
+            .map(|i| {
+                let row = (0..self.nb_poles_v)
+                    .map(|j| self.weighted_pole(i, j))
+                    .collect::<Vec<_>>();
+                iso_pole(v, &self.knots_v, self.degree_v, &row)
+            })
+            .collect();
+        self.iso_curve(self.degree_u, &self.knots_u, poles)
+    }
+
+    fn iso_curve(
+        &self,
+        degree: usize,
+        knots: &BSplineKnots,
+        poles: Vec<BSplinePole<P>>,
+    ) -> BSpline<P> {
+        BSpline::try_new(degree, knots.clone(), poles, self.use_rational)
+            .expect("Iso-curve poles match the knots of the surface.")
+    }
+}
+
+/// Pole of an iso-curve, the curve of weighted `poles` evaluated at `t` as `(P, w)`.
+fn iso_pole<P: ControlPoint>(
+    t: Real,
+    knots: &BSplineKnots,
+    degree: usize,
+    poles: &[BSplinePole<P>],
+) -> BSplinePole<P> {
+    let periodic = knots.is_periodic();
+    let t = periodic_parameter(t, knots);
+    let span = knots.get_span_index(t);
+    let first = knots.get_pole_index(span, degree, periodic);
+    let mut local = (0..=degree)
+        .map(|a| poles[(first + a) % poles.len()])
+        .collect::<Vec<_>>();
+    let knot_slice = knots.get_knots_bounds_slice(span, degree, periodic);
+    let pw = de_boor_homogeneous_in_place(t, knot_slice, &mut local, degree);
+    BSplinePole {
+        pole: pw.pole * (1. / pw.weight),
+        weight: pw.weight,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::Vec3;
+
+    /// Rational surface with a knot inside each direction, at `u = 0.4` and `v = 0.5`.
+    fn surface() -> BSplineSurface {
+        let flatten_u = [0., 0., 0., 0., 0.4, 1., 1., 1., 1.];
+        let knots_u = BSplineKnots::try_from_flatten(&flatten_u, 3, 5, false).unwrap();
+        let flatten_v = [0., 0., 0., 0.5, 1., 1., 1.];
+        let knots_v = BSplineKnots::try_from_flatten(&flatten_v, 2, 4, false).unwrap();
+        let poles = (0..5)
+            .map(|i| {
+                (0..4)
+                    .map(|j| {
+                        let k = (i * 4 + j) as Real;
+                        BSplinePole {
+                            pole: Vec3::new(i as Real, j as Real, (0.7 * k).sin()),
+                            weight: 1. + 0.5 * (1.3 * k).cos(),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        BSplineSurface::try_new(3, 2, knots_u, knots_v, poles, true).unwrap()
+    }
+
+    #[test]
+    fn iso_curves_follow_rational_surface() {
+        let surface = surface();
+        let params = [0., 0.25, 0.4, 0.5, 0.8, 1.];
+        for &fixed in &params {
+            let (along_u, along_v) = (surface.iso_curve_u(fixed), surface.iso_curve_v(fixed));
+            assert!(along_u.use_rational() && along_v.use_rational());
+            for &t in &params {
+                assert!(along_u.interop(t).distance(surface.interop(fixed, t)) < 1e-5);
+                assert!(along_v.interop(t).distance(surface.interop(t, fixed)) < 1e-5);
+            }
+        }
+    }
+}
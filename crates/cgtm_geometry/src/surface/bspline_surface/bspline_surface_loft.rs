@@ -0,0 +1,308 @@
+use crate::curve::bspline::{
+    bspline_approximation::BSplineApproximation,
+    bspline_data_knots::BSplineDataKnotsAlgo,
+    bspline_interpolation::{BSplineInterpolation, InterpolationSystem},
+    bspline_knots::{BSplineFeature, BSplineKnotsAlgo},
+    bspline_poles::BSplinePole,
+    BSpline,
+};
+use crate::precision::{Real, Vec3, Vec4};
+
+use super::{average_parameters, bspline_surface_error::BSplineSurfaceLoftError, BSplineSurface};
+
+/// Skinning of section curves, The NURBS Book 10.3.
+///
+/// Sections are made compatible, see [`BSpline::make_compatible`], and become the
+/// u-curves of the surface. Their poles are then interpolated, or approximated, across
+/// the sections along v.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceLoft {
+    /// Degree along v, lowered to the number of sections minus one when above it.
+    pub degree: usize,
+    pub data_knots_algo: BSplineDataKnotsAlgo,
+    /// Number of poles along v to approximate the sections, `None` interpolates them.
+    pub nb_poles: Option<usize>,
+    /// Loops from the last section back to the first, the surface is periodic in v.
+    pub closed: bool,
+    /// Derivative `S_v` along the first section, open interpolating lofts only.
+    pub start_tangent: Option<Vec3>,
+    /// Derivative `S_v` along the last section, open interpolating lofts only.
+    pub end_tangent: Option<Vec3>,
+}
+
+impl BSplineSurfaceLoft {
+    /// Surface through `sections`, in order. Tangents are exact for non-rational
+    /// sections, rational ones get them in homogeneous space.
+    pub fn try_loft(
+        &self,
+        sections: &[BSpline],
+    ) -> Result<BSplineSurface, BSplineSurfaceLoftError> {
+        self.check(sections)?;
+        let mut sections = sections.to_vec();
+        BSpline::make_compatible(&mut sections)?;
+        let vs = self.average_parameters(&sections);
+        self.loft_compatible(&sections, &vs)
+    }
+
+    /// Same as [`BSplineSurfaceLoft::try_loft`], with the parameter of each section
+    /// given instead of generated by `data_knots_algo`. Closed lofts take one more
+    /// parameter, looping back to the first section.
+    pub fn try_loft_with_parameters(
+        &self,
+        sections: &[BSpline],
+        v_vector: &[Real],
+    ) -> Result<BSplineSurface, BSplineSurfaceLoftError> {
+        self.check(sections)?;
+        let mut sections = sections.to_vec();
+        BSpline::make_compatible(&mut sections)?;
+        self.loft_compatible(&sections, v_vector)
+    }
+
+    fn check(&self, sections: &[BSpline]) -> Result<(), BSplineSurfaceLoftError> {
+        if sections.len() < 2 {
+            return Err(BSplineSurfaceLoftError::TooFewSections(sections.len()));
+        }
+        let has_tangents = self.start_tangent.is_some() || self.end_tangent.is_some();
+        if has_tangents && (self.closed || self.nb_poles.is_some()) {
+            return Err(BSplineSurfaceLoftError::TangentsNotSupported);
+        }
+        if self.closed && self.nb_poles.is_some() {
+            return Err(BSplineSurfaceLoftError::ClosedApproximation);
+        }
+        Ok(())
+    }
+
+    /// Loft of sections already compatible, `vs` the parameters across them.
+    fn loft_compatible(
+        &self,
+        sections: &[BSpline],
+        vs: &[Real],
+    ) -> Result<BSplineSurface, BSplineSurfaceLoftError> {
+        let use_rational = sections[0].use_rational;
+        let nb_poles_u = sections[0].poles.len();
+
+        // Homogeneous poles across the sections, one row per pole of a section.
+        let homogeneous = sections
+            .iter()
+            .map(|s| s.homogeneous_poles())
+            .collect::<Vec<_>>();
+        let rows = (0..nb_poles_u)
+            .map(|i| homogeneous.iter().map(|pw| pw[i]).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let degree = self.degree.min(sections.len() - 1).max(1);
+
+        let (knots_v, rows) = match self.nb_poles {
+            Some(nb_poles) => {
+                let approximation = BSplineApproximation {
+                    degree,
+                    nb_poles,
+                    feature: BSplineFeature::ClampAll,
+                    knots_algo: BSplineKnotsAlgo::Uniform(nb_poles),
+                    data_knots_algo: self.data_knots_algo,
+                };
+                let curves = rows
+                    .iter()
+                    .map(|row| approximation.try_approximate_with_parameters(row, vs))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let knots = curves[0].knots.clone();
+                let rows = curves
+                    .into_iter()
+                    .map(|c| c.poles.into_iter().map(|p| p.pole).collect())
+                    .collect::<Vec<Vec<Vec4>>>();
+                (knots, rows)
+            }
+            None => {
+                let system = if self.closed {
+                    InterpolationSystem::try_new_periodic(degree, vs)?
+                } else {
+                    InterpolationSystem::try_new(
+                        degree,
+                        vs,
+                        self.start_tangent.is_some(),
+                        self.end_tangent.is_some(),
+                    )?
+                };
+                // `(w P)' = w P'` with the weight constant across the end section.
+                let tangent = |t: Option<Vec3>, pw: Vec4| t.map(|t| (pw.w * t).extend(0.));
+                let rows = rows
+                    .iter()
+                    .map(|row| {
+                        let start = tangent(self.start_tangent, row[0]);
+                        let end = tangent(self.end_tangent, row[row.len() - 1]);
+                        system.solve(row, start, end)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (system.knots, rows)
+            }
+        };
+
+        let poles = rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|pw| BSplinePole {
+                        pole: pw.truncate() / pw.w,
+                        weight: pw.w,
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(BSplineSurface::try_new(
+            sections[0].degree,
+            degree,
+            sections[0].knots.clone(),
+            knots_v,
+            poles,
+            use_rational,
+        )?)
+    }
+
+    /// Parameters across the sections, generated per pole of a section and averaged,
+    /// uniform if no pole moves. Closed lofts end with the parameter looping back to the
+    /// first section.
+    fn average_parameters(&self, sections: &[BSpline]) -> Vec<Real> {
+        let interpolation = BSplineInterpolation {
+            degree: self.degree,
+            data_knots_algo: self.data_knots_algo,
+        };
+        let nb_params = sections.len() + usize::from(self.closed);
+        // Poles at the same place in every section give no parameters.
+        let generated = (0..sections[0].poles.len()).map(|i| {
+            let sequence = sections.iter().map(|s| s.poles[i].pole).collect::<Vec<_>>();
+            if self.closed {
+                interpolation.normalized_closed_parameters(&sequence)
+            } else {
+                interpolation.normalized_parameters(&sequence)
+            }
+        });
+        let uniform = (0..nb_params)
+            .map(|k| k as Real / (nb_params - 1) as Real)
+            .collect();
+        average_parameters(generated, uniform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::bspline_knots::BSplineKnots;
+    use crate::precision::consts;
+
+    /// Cubic section at height `z`, its inner knot moving with `k` so sections need
+    /// to be made compatible.
+    fn section(k: usize, z: Real) -> BSpline {
+        let knot = 0.3 + 0.1 * (k % 3) as Real;
+        let flatten = [0., 0., 0., 0., knot, 1., 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 3, 5, false).unwrap();
+        let poles = (0..5)
+            .map(|i| BSplinePole {
+                pole: Vec3::new(i as Real, 0.3 * (i as Real + z).sin(), z),
+                weight: 1.,
+            })
+            .collect();
+        BSpline::try_new(3, knots, poles, false).unwrap()
+    }
+
+    fn loft() -> BSplineSurfaceLoft {
+        BSplineSurfaceLoft {
+            degree: 3,
+            data_knots_algo: BSplineDataKnotsAlgo::ChordLength,
+            nb_poles: None,
+            closed: false,
+            start_tangent: None,
+            end_tangent: None,
+        }
+    }
+
+    fn deviation(surface: &BSplineSurface, section: &BSpline, v: Real) -> Real {
+        (0..=20)
+            .map(|i| i as Real / 20.)
+            .map(|u| surface.interop(u, v).distance(section.interop(u)))
+            .fold(0., Real::max)
+    }
+
+    #[test]
+    fn loft_interpolates_sections() {
+        let vs = [0., 0.2, 0.5, 0.6, 1.];
+        let sections = vs.map(|v| section((10. * v) as usize, 2. * v));
+        let surface = loft().try_loft_with_parameters(&sections, &vs).unwrap();
+        for (section, &v) in sections.iter().zip(&vs) {
+            assert!(deviation(&surface, section, v) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn loft_approximates_sections() {
+        // Poles of the sections are smooth in v, a few poles along v follow them.
+        let vs = (0..8).map(|k| k as Real / 7.).collect::<Vec<_>>();
+        let sections = vs.iter().map(|&v| section(0, v)).collect::<Vec<_>>();
+        let approximating = BSplineSurfaceLoft {
+            nb_poles: Some(5),
+            ..loft()
+        };
+        let surface = approximating
+            .try_loft_with_parameters(&sections, &vs)
+            .unwrap();
+        assert_eq!(surface.nb_poles_v(), 5);
+        for (section, &v) in sections.iter().zip(&vs) {
+            assert!(deviation(&surface, section, v) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn closed_loft_is_periodic() {
+        // Sections turning around the z axis.
+        let sections = (0..6)
+            .map(|k| {
+                let mut section = section(k, 0.);
+                section.try_translate(Vec3::new(1., 0., 0.)).unwrap();
+                let angle = k as Real * consts::TAU / 6.;
+                section.try_rotate(Vec3::ZERO, Vec3::Z, angle).unwrap();
+                section
+            })
+            .collect::<Vec<_>>();
+        let closed = BSplineSurfaceLoft {
+            closed: true,
+            ..loft()
+        };
+        let surface = closed.try_loft(&sections).unwrap();
+        assert!(surface.is_periodic_v());
+        let dv = surface.domain_v();
+        for i in 0..=10 {
+            let u = i as Real / 10.;
+            let (start, end) = (
+                surface.derivatives(u, dv.lower, 1),
+                surface.derivatives(u, dv.upper, 1),
+            );
+            assert!(start[0][0].distance(end[0][0]) < 1e-4);
+            assert!(start[0][1].distance(end[0][1]) < 1e-3 * start[0][1].length());
+        }
+        let mut sections = sections;
+        BSpline::make_compatible(&mut sections).unwrap();
+        let vs = closed.average_parameters(&sections);
+        for (section, &v) in sections.iter().zip(&vs) {
+            assert!(deviation(&surface, section, v) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn loft_end_tangents() {
+        let vs = [0., 0.5, 1.];
+        let sections = vs.map(|v| section(0, 2. * v));
+        let (start, end) = (Vec3::new(0., 1., 2.), Vec3::new(1., 0., 3.));
+        let tangent = BSplineSurfaceLoft {
+            start_tangent: Some(start),
+            end_tangent: Some(end),
+            ..loft()
+        };
+        let surface = tangent.try_loft_with_parameters(&sections, &vs).unwrap();
+        for i in 0..=10 {
+            let u = i as Real / 10.;
+            assert!(surface.derivatives(u, 0., 1)[0][1].distance(start) < 1e-4);
+            assert!(surface.derivatives(u, 1., 1)[0][1].distance(end) < 1e-4);
+        }
+        for (section, &v) in sections.iter().zip(&vs) {
+            assert!(deviation(&surface, section, v) < 1e-4);
+        }
+    }
+}
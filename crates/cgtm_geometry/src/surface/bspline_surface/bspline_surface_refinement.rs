@@ -0,0 +1,71 @@
+use crate::curve::bspline::{bspline_error::BSplineError, BSpline};
+
+use super::{bspline_surface_error::BSplineSurfaceError, BSplineSurface};
+
+impl BSplineSurface {
+    /// Poles `(i, j)` for all `i` as a curve along u, with the knots of u.
+    pub(crate) fn column_curve(&self, j: usize) -> BSpline {
+        BSpline {
+            degree: self.degree_u,
+            knots: self.knots_u.clone(),
+            poles: (0..self.nb_poles_u).map(|i| *self.pole(i, j)).collect(),
+            is_periodic: self.is_periodic_u(),
+            use_rational: self.use_rational,
+        }
+    }
+
+    /// Same surface with u and v swapped, `S'(v, u) = S(u, v)`.
+    pub fn transposed(&self) -> Self {
+        Self {
+            degree_u: self.degree_v,
+            degree_v: self.degree_u,
+            knots_u: self.knots_v.clone(),
+            knots_v: self.knots_u.clone(),
+            poles: (0..self.nb_poles_v)
+                .flat_map(|j| (0..self.nb_poles_u).map(move |i| *self.pole(i, j)))
+                .collect(),
+            nb_poles_u: self.nb_poles_v,
+            nb_poles_v: self.nb_poles_u,
+            use_rational: self.use_rational,
+        }
+    }
+
+    /// Gives surfaces the same degrees, knots and rationality in each direction, their
+    /// shapes unchanged.
+    ///
+    /// Columns, then rows, of all surfaces are made compatible together, see
+    /// [`BSpline::make_compatible`], so both domains become `[0, 1]`. On error,
+    /// `surfaces` are left unchanged.
+    pub fn make_compatible(surfaces: &mut [BSplineSurface]) -> Result<(), BSplineSurfaceError> {
+        let mut compatible = surfaces.to_vec();
+        Self::make_columns_compatible(&mut compatible).map_err(BSplineSurfaceError::U)?;
+        compatible.iter_mut().for_each(|s| *s = s.transposed());
+        Self::make_columns_compatible(&mut compatible).map_err(BSplineSurfaceError::V)?;
+        compatible.iter_mut().for_each(|s| *s = s.transposed());
+        surfaces.clone_from_slice(&compatible);
+        Ok(())
+    }
+
+    fn make_columns_compatible(surfaces: &mut [BSplineSurface]) -> Result<(), BSplineError> {
+        let mut columns = surfaces
+            .iter()
+            .flat_map(|s| (0..s.nb_poles_v).map(|j| s.column_curve(j)))
+            .collect::<Vec<_>>();
+        BSpline::make_compatible(&mut columns)?;
+        let mut columns = columns.into_iter();
+        for surface in surfaces.iter_mut() {
+            let own = columns
+                .by_ref()
+                .take(surface.nb_poles_v)
+                .collect::<Vec<_>>();
+            surface.degree_u = own[0].degree;
+            surface.knots_u = own[0].knots.clone();
+            surface.nb_poles_u = own[0].poles.len();
+            surface.use_rational = own[0].use_rational;
+            surface.poles = (0..surface.nb_poles_u)
+                .flat_map(|i| own.iter().map(move |c| c.poles[i]))
+                .collect();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+use crate::curve::bspline::{
+    bspline_data_knots::BSplineDataKnotsAlgo, bspline_error::BSplineError, BSpline,
+};
+use crate::precision::{Mat4, Real, Vec3};
+
+use super::{
+    bspline_surface_error::BSplineSurfaceSweepError, bspline_surface_loft::BSplineSurfaceLoft,
+    BSplineSurface,
+};
+
+/// Steps between consecutive parameters when propagating rotation-minimizing frames.
+const FRAME_SUBSTEPS: usize = 8;
+/// Profile samples per pole when measuring the deviation of a sweep.
+const DEVIATION_SAMPLES: usize = 2;
+/// Sections of the first attempt, doubled until the tolerance is reached.
+const INITIAL_SECTIONS: usize = 5;
+
+/// Orientation of the profile along the path of a single-rail sweep.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum BSplineSweepFrame {
+    /// Tangent, principal normal and binormal of the path. Straight parts keep the
+    /// previous normal.
+    Frenet,
+    /// Normal turning as little as possible around the tangent, by double reflection.
+    #[default]
+    RotationMinimizing,
+    /// Normal towards the vector projected on the normal plane of the path.
+    Fixed(Vec3),
+}
+
+/// Sweep of a profile curve along a path, approximated by a loft through copies of the
+/// profile, The NURBS Book 10.4.
+///
+/// Sections are added until the surface is within `tolerance` of the exact sweep
+/// halfway between them. The surface has the profile along u on `[0, 1]` and the path
+/// along v on `[0, 1]`.
+#[derive(Debug, Clone)]
+pub struct BSplineSurfaceSweep {
+    pub frame: BSplineSweepFrame,
+    /// Scale of the profile around the path, single rail only. Evaluated on its domain
+    /// mapped from the path one, `1` when `None`.
+    pub scale: Option<BSpline<Real>>,
+    /// Rotation of the profile around the path tangent in radians, single rail only.
+    /// Evaluated like `scale`, `0` when `None`.
+    pub twist: Option<BSpline<Real>>,
+    /// Degree along v.
+    pub degree: usize,
+    pub tolerance: Real,
+    /// Most sections allowed to reach `tolerance`.
+    pub max_sections: usize,
+}
+
+impl Default for BSplineSurfaceSweep {
+    fn default() -> Self {
+        Self {
+            frame: BSplineSweepFrame::default(),
+            scale: None,
+            twist: None,
+            degree: 3,
+            tolerance: 1e-3,
+            max_sections: 257,
+        }
+    }
+}
+
+impl BSplineSurfaceSweep {
+    /// Sweeps `profile`, placed at the start of `path`, along `path`.
+    ///
+    /// The profile moves with the frame of the path: its position relative to the path
+    /// start and the first frame is kept, scaled and twisted by the laws.
+    pub fn try_sweep(
+        &self,
+        profile: &BSpline,
+        path: &BSpline,
+    ) -> Result<BSplineSurface, BSplineSurfaceSweepError> {
+        let at = |t: Real| {
+            let domain = path.domain();
+            domain.lower + t * domain.length()
+        };
+        self.try_approximate(profile, |ts| {
+            let frames = self.frames(path, ts)?;
+            let to_first = frame_matrix(frames[0], path.interop(at(0.))).inverse();
+            Ok(ts
+                .iter()
+                .zip(frames)
+                .map(|(&t, frame)| {
+                    let scale = law(&self.scale, t, 1.);
+                    let twist = law(&self.twist, t, 0.);
+                    frame_matrix(frame, path.interop(at(t)))
+                        * Mat4::from_rotation_z(twist)
+                        * Mat4::from_scale(Vec3::splat(scale))
+                        * to_first
+                })
+                .collect())
+        })
+    }
+
+    /// Sweeps `profile` along two rails, its start on `first` and its end on `second`.
+    ///
+    /// The profile is rotated so its chord follows the rails and scaled to their
+    /// distance, its plane turning with the tangent of `first`. Frame and laws do not
+    /// apply.
+    pub fn try_sweep_two_rails(
+        &self,
+        profile: &BSpline,
+        first: &BSpline,
+        second: &BSpline,
+    ) -> Result<BSplineSurface, BSplineSurfaceSweepError> {
+        let (start, end) = (
+            profile.interop(profile.lower_parameter()),
+            profile.interop(profile.upper_parameter()),
+        );
+        let chord = end - start;
+        let x0 = chord
+            .try_normalize()
+            .ok_or(BSplineSurfaceSweepError::DegenerateProfile)?;
+        let at = |rail: &BSpline, t: Real| {
+            let domain = rail.domain();
+            rail.derivatives(domain.lower + t * domain.length(), 1)
+        };
+        // Frame with the chord `x` and the tangent of the first rail in the `(x, z)` plane.
+        let frame = |x: Vec3, tangent: Vec3| {
+            let y = tangent.cross(x).try_normalize()?;
+            Some([x, y, x.cross(y)])
+        };
+        let first_tangent = at(first, 0.)[1];
+        let to_first = frame_matrix(
+            frame(x0, first_tangent).ok_or(BSplineSurfaceSweepError::DegeneratePath)?,
+            start,
+        )
+        .inverse();
+        self.try_approximate(profile, |ts| {
+            ts.iter()
+                .map(|&t| {
+                    let (a, b) = (at(first, t), at(second, t)[0]);
+                    let rails = b - a[0];
+                    let x = rails
+                        .try_normalize()
+                        .ok_or(BSplineSurfaceSweepError::DegeneratePath)?;
+                    let scale = rails.length() / chord.length();
+                    let frame = frame(x, a[1]).ok_or(BSplineSurfaceSweepError::DegeneratePath)?;
+                    Ok(frame_matrix(frame, a[0]) * Mat4::from_scale(Vec3::splat(scale)) * to_first)
+                })
+                .collect()
+        })
+    }
+
+    /// Lofts copies of `profile` moved by `placements`, evaluated at parameters of the
+    /// path in `[0, 1]`, doubling the sections until the deviation is within tolerance.
+    fn try_approximate<F>(
+        &self,
+        profile: &BSpline,
+        placements: F,
+    ) -> Result<BSplineSurface, BSplineSurfaceSweepError>
+    where
+        F: Fn(&[Real]) -> Result<Vec<Mat4>, BSplineSurfaceSweepError>,
+    {
+        let loft = BSplineSurfaceLoft {
+            degree: self.degree,
+            data_knots_algo: BSplineDataKnotsAlgo::Uniform,
+            nb_poles: None,
+            closed: false,
+            start_tangent: None,
+            end_tangent: None,
+        };
+        let domain = profile.domain();
+        let nb_samples = DEVIATION_SAMPLES * profile.poles.len();
+        let samples = (0..=nb_samples)
+            .map(|i| {
+                let s = i as Real / nb_samples as Real;
+                (s, profile.interop(domain.lower + s * domain.length()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut nb_sections = INITIAL_SECTIONS.min(self.max_sections.max(2));
+        loop {
+            // Sections at even indices, deviation checked at odd ones.
+            let nb_params = 2 * nb_sections - 1;
+            let ts = (0..nb_params)
+                .map(|k| k as Real / (nb_params - 1) as Real)
+                .collect::<Vec<_>>();
+            let matrices = placements(&ts)?;
+            let sections = matrices
+                .iter()
+                .step_by(2)
+                .map(|matrix| {
+                    let mut section = profile.clone();
+                    section.try_transform(matrix)?;
+                    Ok(section)
+                })
+                .collect::<Result<Vec<_>, BSplineError>>()?;
+            let vs = ts.iter().step_by(2).copied().collect::<Vec<_>>();
+            let surface = loft.try_loft_with_parameters(&sections, &vs)?;
+
+            let mut deviation: Real = 0.;
+            for k in (1..nb_params).step_by(2) {
+                for &(s, point) in &samples {
+                    let exact = matrices[k].transform_point3(point);
+                    deviation = deviation.max(surface.interop(s, ts[k]).distance(exact));
+                }
+            }
+            if deviation <= self.tolerance {
+                return Ok(surface);
+            }
+            if nb_params > self.max_sections {
+                return Err(BSplineSurfaceSweepError::ToleranceNotReached { deviation });
+            }
+            nb_sections = nb_params;
+        }
+    }
+
+    /// Frames `[normal, binormal, tangent]` of `path` at parameters `ts` in `[0, 1]`,
+    /// increasing from `0`.
+    fn frames(
+        &self,
+        path: &BSpline,
+        ts: &[Real],
+    ) -> Result<Vec<[Vec3; 3]>, BSplineSurfaceSweepError> {
+        let domain = path.domain();
+        let derivatives = |t: Real| path.derivatives(domain.lower + t * domain.length(), 2);
+        let tangent = |ders: &[Vec3]| {
+            ders[1]
+                .try_normalize()
+                .ok_or(BSplineSurfaceSweepError::DegeneratePath)
+        };
+        // Normal towards `direction`, or keeping `previous` where it is along the tangent.
+        let towards = |direction: Vec3, tangent: Vec3, previous: Vec3| {
+            (direction - direction.dot(tangent) * tangent)
+                .try_normalize()
+                .unwrap_or_else(|| {
+                    (previous - previous.dot(tangent) * tangent)
+                        .try_normalize()
+                        .unwrap_or_else(|| tangent.any_orthonormal_vector())
+                })
+        };
+        let frame = |normal: Vec3, tangent: Vec3| [normal, tangent.cross(normal), tangent];
+
+        let ders = derivatives(0.);
+        let t0 = tangent(&ders)?;
+        let mut normal = match self.frame {
+            BSplineSweepFrame::Fixed(direction) => towards(direction, t0, Vec3::ZERO),
+            _ => towards(ders[2], t0, Vec3::ZERO),
+        };
+        let mut frames = vec![frame(normal, t0)];
+        let (mut point, mut previous_tangent) = (ders[0], t0);
+        for w in ts.windows(2) {
+            let substeps = match self.frame {
+                BSplineSweepFrame::RotationMinimizing => FRAME_SUBSTEPS,
+                _ => 1,
+            };
+            for step in 1..=substeps {
+                let t = w[0] + (w[1] - w[0]) * step as Real / substeps as Real;
+                let ders = derivatives(t);
+                let tangent = tangent(&ders)?;
+                normal = match self.frame {
+                    BSplineSweepFrame::Frenet => towards(ders[2], tangent, normal),
+                    BSplineSweepFrame::Fixed(direction) => towards(direction, tangent, normal),
+                    BSplineSweepFrame::RotationMinimizing => {
+                        double_reflection(point, previous_tangent, normal, ders[0], tangent)
+                    }
+                };
+                (point, previous_tangent) = (ders[0], tangent);
+            }
+            frames.push(frame(normal, previous_tangent));
+        }
+        Ok(frames)
+    }
+}
+
+/// Rotation-minimizing normal at `(x1, t1)` from `r0` at `(x0, t0)`, Wang et al. 2008.
+fn double_reflection(x0: Vec3, t0: Vec3, r0: Vec3, x1: Vec3, t1: Vec3) -> Vec3 {
+    let reflect = |v: Vec3, axis: Vec3| {
+        let c = axis.dot(axis);
+        if c <= Real::EPSILON {
+            v
+        } else {
+            v - (2. / c) * axis.dot(v) * axis
+        }
+    };
+    let v1 = x1 - x0;
+    let (r, t) = (reflect(r0, v1), reflect(t0, v1));
+    let r1 = reflect(r, t1 - t);
+    (r1 - r1.dot(t1) * t1).normalize()
+}
+
+/// Matrix mapping x, y and z to `frame` at `origin`.
+fn frame_matrix(frame: [Vec3; 3], origin: Vec3) -> Mat4 {
+    Mat4::from_cols(
+        frame[0].extend(0.),
+        frame[1].extend(0.),
+        frame[2].extend(0.),
+        origin.extend(1.),
+    )
+}
+
+/// Value of `law` at `t` in `[0, 1]` mapped to its domain, `default` without law.
+fn law(law: &Option<BSpline<Real>>, t: Real, default: Real) -> Real {
+    law.as_ref().map_or(default, |law| {
+        let domain = law.domain();
+        law.interop(domain.lower + t * domain.length())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::bspline::{
+        bspline_interpolation::BSplineInterpolation, bspline_knots::BSplineKnots,
+        bspline_poles::BSplinePole,
+    };
+    use crate::precision::consts;
+
+    /// Rational circle of `radius` around `center` in the plane of the unit vectors `x`
+    /// and `y`, starting along `x`.
+    fn circle(center: Vec3, x: Vec3, y: Vec3, radius: Real) -> BSpline {
+        let flatten = [0., 0., 0., 0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 2, 9, false).unwrap();
+        let corners = [(1., 0.), (1., 1.), (0., 1.), (-1., 1.), (-1., 0.)];
+        let corners = corners
+            .iter()
+            .chain(&[(-1., -1.), (0., -1.), (1., -1.), (1., 0.)])
+            .enumerate()
+            .map(|(i, &(a, b))| BSplinePole {
+                pole: center + radius * (a * x + b * y),
+                weight: if i % 2 == 1 { Real::sqrt(0.5) } else { 1. },
+            })
+            .collect();
+        BSpline::try_new(2, knots, corners, true).unwrap()
+    }
+
+    /// Rational half circle from `start` to `end`, bulging towards `up`.
+    fn half_circle(start: Vec3, end: Vec3, up: Vec3) -> BSpline {
+        let flatten = [0., 0., 0., 0.5, 0.5, 1., 1., 1.];
+        let knots = BSplineKnots::try_from_flatten(&flatten, 2, 5, false).unwrap();
+        let (center, x) = ((start + end) / 2., (end - start) / 2.);
+        let y = x.length() * (up - up.dot(x) / x.dot(x) * x).normalize();
+        let poles = [(-1., 0.), (-1., 1.), (0., 1.), (1., 1.), (1., 0.)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(a, b))| BSplinePole {
+                pole: center + a * x + b * y,
+                weight: if i % 2 == 1 { Real::sqrt(0.5) } else { 1. },
+            })
+            .collect();
+        BSpline::try_new(2, knots, poles, true).unwrap()
+    }
+
+    fn line(start: Vec3, end: Vec3) -> BSpline {
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).unwrap();
+        let poles = [start, end]
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .to_vec();
+        BSpline::try_new(1, knots, poles, false).unwrap()
+    }
+
+    /// Linear law from `start` to `end`.
+    fn law(start: Real, end: Real) -> BSpline<Real> {
+        let knots = BSplineKnots::try_from_flatten(&[0., 0., 1., 1.], 1, 2, false).unwrap();
+        let poles = [start, end]
+            .map(|pole| BSplinePole { pole, weight: 1. })
+            .to_vec();
+        BSpline::try_new(1, knots, poles, false).unwrap()
+    }
+
+    /// One turn of a helix of radius 2 and pitch 3, interpolated on `[0, 1]`.
+    fn helix() -> BSpline {
+        let n = 32;
+        let u_vector = (0..=n).map(|k| k as Real / n as Real).collect::<Vec<_>>();
+        let data = u_vector
+            .iter()
+            .map(|&t| {
+                let a = consts::TAU * t;
+                Vec3::new(2. * a.cos(), 2. * a.sin(), 3. * t)
+            })
+            .collect::<Vec<_>>();
+        let interpolation = BSplineInterpolation {
+            degree: 3,
+            data_knots_algo: BSplineDataKnotsAlgo::Uniform,
+        };
+        interpolation
+            .try_interpolate_with_parameters(&data, &u_vector, None, None)
+            .unwrap()
+    }
+
+    /// Surface samples on a grid, `(u, v, point)`.
+    fn grid(surface: &BSplineSurface) -> Vec<(Real, Real, Vec3)> {
+        let n = 24;
+        (0..=n)
+            .flat_map(|i| (0..=n).map(move |j| (i as Real / n as Real, j as Real / n as Real)))
+            .map(|(u, v)| (u, v, surface.interop(u, v)))
+            .collect()
+    }
+
+    /// Largest error of the section at `v` being a circle of `radius(v)` centred on
+    /// `path` in its normal plane.
+    fn circle_deviation(
+        surface: &BSplineSurface,
+        path: &BSpline,
+        radius: impl Fn(Real) -> Real,
+    ) -> Real {
+        grid(surface)
+            .into_iter()
+            .map(|(_, v, point)| {
+                let ders = path.derivatives(v, 1);
+                let offset = point - ders[0];
+                let along = offset.dot(ders[1].normalize()).abs();
+                along.max((offset.length() - radius(v)).abs())
+            })
+            .fold(0., Real::max)
+    }
+
+    #[test]
+    fn sweep_circle_along_line() {
+        let path = line(Vec3::ZERO, Vec3::new(0., 0., 2.));
+        let profile = circle(Vec3::ZERO, Vec3::X, Vec3::Y, 1.);
+        let surface = BSplineSurfaceSweep::default()
+            .try_sweep(&profile, &path)
+            .unwrap();
+        assert!(circle_deviation(&surface, &path, |_| 1.) < 1e-3);
+        // Straight path, the profile only moves along it.
+        for (u, v, point) in grid(&surface) {
+            assert!((profile.interop(u) + Vec3::new(0., 0., 2. * v)).distance(point) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sweep_circle_along_helix() {
+        let path = helix();
+        let ders = path.derivatives(0., 1);
+        let t = ders[1].normalize();
+        let x = Vec3::Z.cross(t).normalize();
+        let profile = circle(ders[0], x, t.cross(x), 0.5);
+        for frame in [
+            BSplineSweepFrame::RotationMinimizing,
+            BSplineSweepFrame::Frenet,
+            BSplineSweepFrame::Fixed(Vec3::Z),
+        ] {
+            let sweep = BSplineSurfaceSweep {
+                frame,
+                ..Default::default()
+            };
+            let surface = sweep.try_sweep(&profile, &path).unwrap();
+            assert!(circle_deviation(&surface, &path, |_| 0.5) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sweep_laws() {
+        let path = line(Vec3::ZERO, Vec3::new(0., 0., 2.));
+        let sweep = BSplineSurfaceSweep {
+            scale: Some(law(1., 2.)),
+            ..Default::default()
+        };
+        let profile = circle(Vec3::ZERO, Vec3::X, Vec3::Y, 1.);
+        let surface = sweep.try_sweep(&profile, &path).unwrap();
+        assert!(circle_deviation(&surface, &path, |v| 1. + v) < 1e-3);
+
+        // A radius turning a quarter around the path.
+        let sweep = BSplineSurfaceSweep {
+            twist: Some(law(0., consts::FRAC_PI_2)),
+            ..Default::default()
+        };
+        let profile = line(Vec3::ZERO, Vec3::X);
+        let surface = sweep.try_sweep(&profile, &path).unwrap();
+        for (u, v, point) in grid(&surface) {
+            let a = consts::FRAC_PI_2 * v;
+            let exact = Vec3::new(u * a.cos(), u * a.sin(), 2. * v);
+            assert!(exact.distance(point) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sweep_two_rails_follows_rails() {
+        let first = helix();
+        let mut second = helix();
+        second.try_scale(Vec3::ZERO, 1.5).unwrap();
+        let (start, end) = (first.interop(0.), second.interop(0.));
+        let profile = half_circle(start, end, Vec3::Z);
+        let surface = BSplineSurfaceSweep::default()
+            .try_sweep_two_rails(&profile, &first, &second)
+            .unwrap();
+        for j in 0..=24 {
+            let v = j as Real / 24.;
+            assert!(surface.interop(0., v).distance(first.interop(v)) < 1e-3);
+            assert!(surface.interop(1., v).distance(second.interop(v)) < 1e-3);
+        }
+    }
+
+    #[test]
+    fn sweep_tolerance_not_reached() {
+        let sweep = BSplineSurfaceSweep {
+            tolerance: 1e-9,
+            max_sections: 5,
+            ..Default::default()
+        };
+        let path = helix();
+        let profile = line(path.interop(0.), path.interop(0.) + Vec3::Z);
+        assert!(matches!(
+            sweep.try_sweep(&profile, &path),
+            Err(BSplineSurfaceSweepError::ToleranceNotReached { .. })
+        ));
+    }
+}